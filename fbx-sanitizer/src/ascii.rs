@@ -0,0 +1,436 @@
+use crate::tree::Node;
+use crate::writer::write_binary;
+use anyhow::{bail, format_err};
+use content_inspector::ContentType::BINARY;
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use std::convert::TryFrom;
+use std::path::Path;
+
+/// Reads an fbx file as binary fbx. ASCII files are parsed and written out with
+/// `writer::write_binary`, so they can be checked and fixed like binary files. Also returns
+/// whether the file was binary to begin with.
+pub fn read_as_binary(path: &Path) -> anyhow::Result<(Vec<u8>, bool)> {
+    let bytes = std::fs::read(path)?;
+    if content_inspector::inspect(&bytes) == BINARY {
+        return Ok((bytes, true));
+    }
+
+    let (version, root) = parse(&String::from_utf8_lossy(&bytes))?;
+    Ok((write_binary(&root, version, None)?, false))
+}
+
+/// Parses an ASCII fbx file into the same tree a binary file has. Returns the file's FBX version
+/// and the root of the tree (see `tree::Node`).
+///
+/// ASCII files don't say how numbers are stored, so each value is given the type the FBX SDK
+/// uses in binary files: ie. object ids and times are 64-bit, vertices are doubles, and
+/// animation keys are floats. Object names are written as `"Class::Name"` in ASCII files, and are
+/// converted to the binary `"Name\x00\x01Class"`.
+pub fn parse(text: &str) -> anyhow::Result<(u32, Node)> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        pos: 0,
+    };
+
+    let mut root = Node::new("", vec![]);
+    root.children = parser.nodes("")?;
+    if parser.pos != parser.tokens.len() {
+        bail!("Line {}: expected a node.", parser.line());
+    }
+
+    let version = root
+        .child("FBXHeaderExtension")
+        .and_then(|header| header.child("FBXVersion"))
+        .and_then(|version| match version.attributes.get(0) {
+            Some(AttributeValue::I32(v)) => u32::try_from(*v).ok(),
+            _ => None,
+        });
+    match version {
+        Some(version) if (7000..8000).contains(&version) => Ok((version, root)),
+        Some(version) => bail!(
+            "FBX {}.{} files are not supported. Only FBX 7.x files can be read. Re-export the file as FBX 7.4 or later.",
+            version / 1000,
+            version % 1000 / 100
+        ),
+        None => bail!("The file has no FBXVersion. Is it an fbx file?"),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A node name, or the `a` of an array, followed by `:`.
+    Key(String),
+    String(String),
+    /// A number, or a bare letter for booleans.
+    Word(String),
+    Comma,
+    Open,
+    Close,
+    /// The `*` before an array's length.
+    Star,
+}
+
+/// Splits the file into tokens, and the line each is on. Comments (from `;` to the end of the
+/// line) are skipped.
+fn tokenize(text: &str) -> anyhow::Result<Vec<(usize, Token)>> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() || c == '\u{feff}' => continue,
+            ';' => {
+                while chars.peek().map_or(false, |&(_, c)| c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            ',' => Token::Comma,
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '*' => Token::Star,
+            '"' => {
+                let start_line = line;
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, c)) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            string.push(c);
+                        }
+                        None => bail!("Line {}: unterminated string.", start_line),
+                    }
+                }
+                // Quotes inside strings are escaped like in xml.
+                Token::String(string.replace("&quot;", "\""))
+            }
+            ':' => bail!("Line {}: unexpected ':'.", line),
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || ",{}:;\"*".contains(c) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let word = text[start..end].to_owned();
+                if chars.peek().map(|&(_, c)| c) == Some(':') {
+                    chars.next();
+                    Token::Key(word)
+                } else {
+                    Token::Word(word)
+                }
+            }
+        };
+        tokens.push((line, token));
+    }
+    Ok(tokens)
+}
+
+/// A value as written in the file, before it is given the type it has in binary files.
+enum Value {
+    Number(Number),
+    String(String),
+    Bool(bool),
+    Array(Vec<Number>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn parse(word: &str) -> Option<Number> {
+        if let Ok(v) = word.parse::<i64>() {
+            return Some(Number::Int(v));
+        }
+        if let Ok(v) = word.parse::<f64>() {
+            return Some(Number::Float(v));
+        }
+
+        // The FBX SDK on Windows writes infinities and NaNs as ie. "1.#INF" and "-1.#IND".
+        let (sign, magnitude) = match word.strip_prefix('-') {
+            Some(magnitude) => (-1.0, magnitude),
+            None => (1.0, word),
+        };
+        match magnitude {
+            "1.#INF" => Some(Number::Float(sign * f64::INFINITY)),
+            "1.#IND" | "1.#QNAN" | "1.#SNAN" => Some(Number::Float(f64::NAN)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(v) => v as f64,
+            Number::Float(v) => v,
+        }
+    }
+
+    fn as_i64(self) -> anyhow::Result<i64> {
+        match self {
+            Number::Int(v) => Ok(v),
+            Number::Float(v) => bail!("Expected an integer, found {}.", v),
+        }
+    }
+
+    fn as_i32(self) -> anyhow::Result<i32> {
+        Ok(i32::try_from(self.as_i64()?)?)
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    /// Returns the current token, and moves past it.
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// The line of the current token, for errors.
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos.min(self.tokens.len().saturating_sub(1)))
+            .map_or(1, |(line, _)| *line)
+    }
+
+    fn expect(&mut self, expected: Token, description: &str) -> anyhow::Result<()> {
+        let line = self.line();
+        match self.bump() {
+            Some(token) if token == expected => Ok(()),
+            _ => bail!("Line {}: expected {}.", line, description),
+        }
+    }
+
+    /// Parses nodes until the `}` closing `parent`, or the end of the file.
+    fn nodes(&mut self, parent: &str) -> anyhow::Result<Vec<Node>> {
+        let mut nodes = vec![];
+        while let Some(Token::Key(_)) = self.peek() {
+            nodes.push(self.node(parent)?);
+        }
+        Ok(nodes)
+    }
+
+    fn node(&mut self, parent: &str) -> anyhow::Result<Node> {
+        let line = self.line();
+        let name = match self.bump() {
+            Some(Token::Key(name)) => name,
+            _ => unreachable!("nodes() only calls node() on a key"),
+        };
+
+        let mut values = vec![];
+        if matches!(
+            self.peek(),
+            Some(Token::String(_)) | Some(Token::Word(_)) | Some(Token::Star)
+        ) {
+            values.push(self.value()?);
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                values.push(self.value()?);
+            }
+        }
+
+        let attributes = typed_attributes(&name, parent, values)
+            .map_err(|e| format_err!("Line {}: {}", line, e))?;
+        let mut node = Node::new(name, attributes);
+        if self.peek() == Some(&Token::Open) {
+            self.pos += 1;
+            node.children = self.nodes(&node.name)?;
+            self.expect(Token::Close, "'}' or a node")?;
+        }
+        Ok(node)
+    }
+
+    fn value(&mut self) -> anyhow::Result<Value> {
+        let line = self.line();
+        match self.bump() {
+            Some(Token::String(s)) => Ok(Value::String(s)),
+            Some(Token::Word(word)) => {
+                if let Some(number) = Number::parse(&word) {
+                    Ok(Value::Number(number))
+                } else if word.len() == 1 {
+                    // Booleans are written as a letter, like in binary files: 'Y' or 'T'. Only the
+                    // lowest bit matters.
+                    Ok(Value::Bool(word.as_bytes()[0] & 1 == 1))
+                } else {
+                    bail!("Line {}: unexpected value '{}'.", line, word)
+                }
+            }
+            // Arrays are written as `*3 { a: 1,2,3 }`.
+            Some(Token::Star) => {
+                let len = match self.bump() {
+                    Some(Token::Word(len)) => len
+                        .parse::<usize>()
+                        .map_err(|_| format_err!("Line {}: invalid array length.", line))?,
+                    _ => bail!("Line {}: expected an array length.", line),
+                };
+                self.expect(Token::Open, "'{'")?;
+                self.expect(Token::Key("a".to_owned()), "'a:'")?;
+
+                let mut values = vec![];
+                if let Some(Token::Word(_)) = self.peek() {
+                    values.push(self.number()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        values.push(self.number()?);
+                    }
+                }
+                self.expect(Token::Close, "'}'")?;
+
+                if values.len() != len {
+                    bail!(
+                        "Line {}: array has {} values, but its length is {}.",
+                        line,
+                        values.len(),
+                        len
+                    );
+                }
+                Ok(Value::Array(values))
+            }
+            _ => bail!("Line {}: expected a value.", line),
+        }
+    }
+
+    fn number(&mut self) -> anyhow::Result<Number> {
+        let line = self.line();
+        match self.bump() {
+            Some(Token::Word(word)) => Number::parse(&word)
+                .ok_or_else(|| format_err!("Line {}: invalid number '{}'.", line, word)),
+            _ => bail!("Line {}: expected a number.", line),
+        }
+    }
+}
+
+/// The binary type of a number that isn't in an array.
+enum ScalarType {
+    I32,
+    I64,
+    F64,
+}
+
+/// Gives each value of a node the type the FBX SDK uses for it in binary files.
+fn typed_attributes(
+    name: &str,
+    parent: &str,
+    values: Vec<Value>,
+) -> anyhow::Result<Vec<AttributeValue>> {
+    // The type of a property decides the type of its values.
+    let property_type = match (name, values.get(1)) {
+        ("P", Some(Value::String(property_type))) => property_type.clone(),
+        _ => String::new(),
+    };
+
+    let mut attributes = vec![];
+    for (i, value) in values.into_iter().enumerate() {
+        attributes.push(match value {
+            Value::String(s) if is_object_name(name, parent, i) => {
+                AttributeValue::String(binary_object_name(&s))
+            }
+            Value::String(s) => AttributeValue::String(s),
+            Value::Bool(v) => AttributeValue::Bool(v),
+            Value::Number(Number::Float(v)) => AttributeValue::F64(v),
+            Value::Number(Number::Int(v)) => match scalar_type(name, parent, i, &property_type) {
+                // Integers too large for 32 bits are kept, rather than truncated.
+                ScalarType::I32 => match i32::try_from(v) {
+                    Ok(v) => AttributeValue::I32(v),
+                    Err(_) => AttributeValue::I64(v),
+                },
+                ScalarType::I64 => AttributeValue::I64(v),
+                ScalarType::F64 => AttributeValue::F64(v as f64),
+            },
+            Value::Array(values) => typed_array(name, &values)?,
+        });
+    }
+    Ok(attributes)
+}
+
+fn scalar_type(name: &str, parent: &str, index: usize, property_type: &str) -> ScalarType {
+    match (name, index) {
+        // Object ids.
+        _ if parent == "Objects" && index == 0 => ScalarType::I64,
+        ("Document", 0) | ("RootNode", 0) | ("Node", 0) | ("C", 1) | ("C", 2) => ScalarType::I64,
+        // Times.
+        ("LocalTime", _) | ("ReferenceTime", _) => ScalarType::I64,
+        ("Default", _)
+        | ("Position", _)
+        | ("Up", _)
+        | ("LookAt", _)
+        | ("AudioColor", _)
+        | ("CameraOrthoZoom", _) => ScalarType::F64,
+        ("P", i) if i >= 4 => match property_type {
+            "KTime" | "ULongLong" => ScalarType::I64,
+            "int" | "Integer" | "enum" | "bool" | "Bool" | "Visibility Inheritance" => {
+                ScalarType::I32
+            }
+            _ => ScalarType::F64,
+        },
+        _ => ScalarType::I32,
+    }
+}
+
+fn typed_array(name: &str, values: &[Number]) -> anyhow::Result<AttributeValue> {
+    Ok(match name {
+        "KeyTime" => AttributeValue::ArrI64(
+            values
+                .iter()
+                .map(|v| v.as_i64())
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        "KeyValueFloat" | "KeyAttrDataFloat" => {
+            AttributeValue::ArrF32(values.iter().map(|v| v.as_f64() as f32).collect())
+        }
+        "Visibility" => AttributeValue::ArrBool(values.iter().map(|v| v.as_f64() != 0.0).collect()),
+        _ if name.ends_with("Index")
+            || matches!(
+                name,
+                "Edges"
+                    | "Materials"
+                    | "Smoothing"
+                    | "Indexes"
+                    | "PolygonGroup"
+                    | "KeyAttrFlags"
+                    | "KeyAttrRefCount"
+            ) =>
+        {
+            AttributeValue::ArrI32(
+                values
+                    .iter()
+                    .map(|v| v.as_i32())
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        }
+        _ => AttributeValue::ArrF64(values.iter().map(|v| v.as_f64()).collect()),
+    })
+}
+
+/// True if the value is an object's name, which is written differently in binary files.
+fn is_object_name(name: &str, parent: &str, index: usize) -> bool {
+    (parent == "Objects" && index == 1) || (name == "SceneInfo" && index == 0)
+}
+
+/// Converts an object name from ASCII's `"Class::Name"` to binary's `"Name\x00\x01Class"`.
+fn binary_object_name(name: &str) -> String {
+    match name.find("::") {
+        Some(i) => format!("{}\u{0}\u{1}{}", &name[i + 2..], &name[..i]),
+        None => name.to_owned(),
+    }
+}
@@ -1,24 +1,17 @@
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
 use crate::utils::geo_name;
 use cgmath::{Point3, Vector3};
 use fbxcel_dom::v7400::object::geometry::TypedGeometryHandle;
 use fbxcel_dom::v7400::object::TypedObjectHandle;
 use fbxcel_dom::v7400::Document;
-use itertools::Itertools;
-use ordered_float::NotNan;
+use serde::Deserialize;
 
-fn point_to_not_nan(p: Point3<f64>) -> anyhow::Result<Point3<NotNan<f64>>> {
-    Ok(Point3::<NotNan<f64>>::new(
-        NotNan::<f64>::new(p.x)?,
-        NotNan::<f64>::new(p.y)?,
-        NotNan::<f64>::new(p.z)?,
-    ))
-}
-
-fn max(p1: Point3<NotNan<f64>>, p2: Point3<NotNan<f64>>) -> Point3<NotNan<f64>> {
+fn max(p1: Point3<f64>, p2: Point3<f64>) -> Point3<f64> {
     Point3::new(p1.x.max(p2.x), p1.y.max(p2.y), p1.z.max(p2.z))
 }
 
-fn min(p1: Point3<NotNan<f64>>, p2: Point3<NotNan<f64>>) -> Point3<NotNan<f64>> {
+fn min(p1: Point3<f64>, p2: Point3<f64>) -> Point3<f64> {
     Point3::new(p1.x.min(p2.x), p1.y.min(p2.y), p1.z.min(p2.z))
 }
 fn vec_to_string<T>(v: Vector3<T>) -> String
@@ -30,61 +23,114 @@ where
 
 /// Verifies that a raw mesh geometry is not too small or too large. Small or large models
 /// can cause Unity's UV unwrapper to fail.
-pub fn verify(doc: &Document) -> anyhow::Result<Option<String>> {
-    const MIN_BOUND_SIZE: f64 = 0.0001;
-    const MAX_BOUND_SIZE: f64 = 1000.0;
-    for obj in doc.objects() {
-        if let TypedObjectHandle::Geometry(geo) = obj.get_typed() {
-            if let TypedGeometryHandle::Mesh(m) = geo {
-                let max_bound = m
-                    .polygon_vertices()?
-                    .raw_control_points()?
-                    .map(|p| point_to_not_nan(Point3::new(p.x, p.y, p.z)))
-                    .fold_ok(
-                        point_to_not_nan(Point3::new(f64::MIN, f64::MIN, f64::MIN))?,
-                        max,
-                    )?;
+///
+/// Vertices that are NaN or infinite are left out of the bounds. They are reported by
+/// `no_invalid_values`.
+///
+/// This is only necessary for light baking, so it is opt-in.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoundingBoxSize {
+    /// Meshes fail if their bounds are smaller than this on *all* axes.
+    pub min_bound_size: f64,
+    /// Meshes fail if their bounds are larger than this on *any* axis.
+    pub max_bound_size: f64,
+}
 
-                let min_bound = m
-                    .polygon_vertices()?
-                    .raw_control_points()?
-                    .map(|p| point_to_not_nan(Point3::new(p.x, p.y, p.z)))
-                    .fold_ok(
-                        point_to_not_nan(Point3::new(f64::MAX, f64::MAX, f64::MAX))?,
-                        min,
-                    )?;
+impl Default for BoundingBoxSize {
+    fn default() -> Self {
+        BoundingBoxSize {
+            min_bound_size: 0.0001,
+            max_bound_size: 1000.0,
+        }
+    }
+}
 
-                // Check fails if *all* of the bounds are smaller than the min.
-                let bounds: Vector3<NotNan<f64>> = max_bound - min_bound;
-                if *bounds.x < MIN_BOUND_SIZE
-                    && *bounds.y < MIN_BOUND_SIZE
-                    && *bounds.z < MIN_BOUND_SIZE
-                {
-                    return Ok(Some(format!(
-                        "The bounds (size) of the mesh [{}] are too small. Meshes must be larger than [{}]. The mesh \
-                        bounds are of size {}",
-                        geo_name(&geo).unwrap_or("No Name"),
-                        MIN_BOUND_SIZE,
-                        vec_to_string(bounds)
-                    )));
-                }
+impl Check for BoundingBoxSize {
+    fn id(&self) -> &'static str {
+        "bounding_box_size"
+    }
 
-                // Check fails if *any* of the bounds are larger than the max.
-                if *bounds.x > MAX_BOUND_SIZE
-                    || *bounds.y > MAX_BOUND_SIZE
-                    || *bounds.z > MAX_BOUND_SIZE
-                {
-                    return Ok(Some(format!(
-                        "The bounds (size) of the mesh [{}] are too big. Meshes must be smaller than [{}]. The mesh \
-                        bounds are of size {}",
-                        geo_name(&geo).unwrap_or("No Name"),
-                        MAX_BOUND_SIZE,
-                        vec_to_string(bounds)
-                    )));
+    fn title(&self) -> &'static str {
+        "Mesh size is wrong"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies no mesh is massive or tiny. Extreme sizes can cause \"Generate Lightmap UVs\" in Unity to fail."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "Meshes that are too small or too large can cause Unity's UV unwrapper (\"Generate \
+         Lightmap UVs\") to fail.\n\n\
+         Vertices that are NaN or infinite are left out of the bounds, and reported by \
+         no_invalid_values instead. This is only necessary for light baking, so it is opt-in."
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+        for obj in doc.objects() {
+            if let TypedObjectHandle::Geometry(geo) = obj.get_typed() {
+                if let TypedGeometryHandle::Mesh(m) = geo {
+                    let points: Vec<Point3<f64>> = m
+                        .polygon_vertices()?
+                        .raw_control_points()?
+                        .map(|p| Point3::new(p.x, p.y, p.z))
+                        .filter(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite())
+                        .collect();
+                    if points.is_empty() {
+                        continue;
+                    }
+
+                    let max_bound = points
+                        .iter()
+                        .fold(Point3::new(f64::MIN, f64::MIN, f64::MIN), |a, &b| max(a, b));
+                    let min_bound = points
+                        .iter()
+                        .fold(Point3::new(f64::MAX, f64::MAX, f64::MAX), |a, &b| min(a, b));
+
+                    // Check fails if *all* of the bounds are smaller than the min.
+                    let bounds: Vector3<f64> = max_bound - min_bound;
+                    if bounds.x < self.min_bound_size
+                        && bounds.y < self.min_bound_size
+                        && bounds.z < self.min_bound_size
+                    {
+                        errors.push(Diagnostic::new(format!(
+                            "The bounds (size) of the mesh [{}] are too small. Meshes must be larger than [{}]. The mesh \
+                            bounds are of size {}",
+                            geo_name(&geo).unwrap_or("No Name"),
+                            self.min_bound_size,
+                            vec_to_string(bounds)
+                        ))
+                        .with_object(&obj, geo_name(&geo).unwrap_or("No Name"))
+                        .measured(vec_to_string(bounds))
+                        .expected(format!("> {}", self.min_bound_size)));
+                        continue;
+                    }
+
+                    // Check fails if *any* of the bounds are larger than the max.
+                    if bounds.x > self.max_bound_size
+                        || bounds.y > self.max_bound_size
+                        || bounds.z > self.max_bound_size
+                    {
+                        errors.push(Diagnostic::new(format!(
+                            "The bounds (size) of the mesh [{}] are too big. Meshes must be smaller than [{}]. The mesh \
+                            bounds are of size {}",
+                            geo_name(&geo).unwrap_or("No Name"),
+                            self.max_bound_size,
+                            vec_to_string(bounds)
+                        ))
+                        .with_object(&obj, geo_name(&geo).unwrap_or("No Name"))
+                        .measured(vec_to_string(bounds))
+                        .expected(format!("< {}", self.max_bound_size)));
+                    }
                 }
             }
         }
-    }
 
-    Ok(None)
+        Ok(errors)
+    }
 }
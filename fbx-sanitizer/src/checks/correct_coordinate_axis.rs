@@ -1,9 +1,15 @@
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
+use crate::fix::axis::{self, AxisConversion};
+use crate::tree::Node;
 use crate::utils::{get_application_name, ApplicationName};
 use anyhow::format_err;
 use cgmath::Vector3;
 use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
 use fbxcel_dom::v7400::document::GlobalSettings;
 use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
+use std::convert::TryFrom;
 
 /// This is a check for blender files generated by Blender 2.90. Unity does not currently support
 /// fbx files generated like this.
@@ -11,36 +17,199 @@ use fbxcel_dom::v7400::Document;
 
 /// In Blender 2.90, it is possible to export a file with the correct rotation, without changing the
 /// axis. This guarantees that the object will not accidentally be counter-rotated when importing into Unity.
-#[allow(unused)]
-pub fn verify(doc: &Document) -> Result<Vec<String>, anyhow::Error> {
-    let axis =
-        get_coordinate_axis(doc).ok_or_else(|| format_err!("Could not find coordinate axis."))?;
+///
+/// The expected axes are configured per application, ie. `maya = { up = "+Y", front = "+Z", coord = "+X" }`.
+///
+/// `--fix` converts the file to the expected axis, and rotates the scene to match, so it looks the
+/// same in Unity. See `fix::axis`. Files with the wrong handedness can't be converted without
+/// mirroring them, and must be re-exported.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorrectCoordinateAxis {
+    pub blender: CoordinateAxis,
+    pub max: CoordinateAxis,
+    pub maya: CoordinateAxis,
+    pub houdini: CoordinateAxis,
+    /// Any other application, or files that don't say which application saved them.
+    pub other: CoordinateAxis,
+}
+
+impl Default for CorrectCoordinateAxis {
+    fn default() -> Self {
+        CorrectCoordinateAxis {
+            blender: coordinate_axis_for_software(&Some(ApplicationName::Blender)),
+            max: coordinate_axis_for_software(&Some(ApplicationName::Max)),
+            maya: coordinate_axis_for_software(&Some(ApplicationName::Maya)),
+            houdini: coordinate_axis_for_software(&Some(ApplicationName::Houdini)),
+            other: coordinate_axis_for_software(&None),
+        }
+    }
+}
+
+impl CorrectCoordinateAxis {
+    /// The coordinate axis a file saved by the given application should have.
+    pub fn expected_axis(&self, application_name: &Option<ApplicationName>) -> &CoordinateAxis {
+        match application_name {
+            Some(ApplicationName::Blender) => &self.blender,
+            Some(ApplicationName::Max) => &self.max,
+            Some(ApplicationName::Maya) => &self.maya,
+            Some(ApplicationName::Houdini) => &self.houdini,
+            Some(ApplicationName::UnknownApplication(_)) | None => &self.other,
+        }
+    }
+}
+
+impl Check for CorrectCoordinateAxis {
+    fn id(&self) -> &'static str {
+        "correct_coordinate_axis"
+    }
 
-    let application_name = get_application_name(doc);
+    fn title(&self) -> &'static str {
+        "Incorrect axis"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies the file is saved with a coordinate axis that will result in a zero rotation. This is unique for each export program."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "Each application exports its own axis system, and Unity counter-rotates objects whose \
+         axes don't match its own. In Blender 2.90, it is possible to export a file with the \
+         correct rotation, without changing the axis. This guarantees that the object will not \
+         accidentally be counter-rotated when importing into Unity.\n\n\
+         The expected axes are configured per application. --fix converts the file to the \
+         expected axis, and rotates the scene to match, so it looks the same in Unity. Files with \
+         the wrong handedness can't be converted without mirroring them, and must be re-exported."
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let axis = get_coordinate_axis(doc)
+            .ok_or_else(|| format_err!("Could not find coordinate axis."))?;
 
-    let correct = coordinate_axis_for_software(&application_name);
+        let application_name = get_application_name(doc);
+
+        let correct = self.expected_axis(&application_name);
+
+        if axis != *correct {
+            return Ok(vec![Diagnostic::new(format!(
+                "File has incorrect Coordinate Axis. Expected [{}] actual [{}]. [{:?}]",
+                correct.display_triplet(),
+                axis.display_triplet(),
+                application_name,
+            ))
+            .measured(axis.display_triplet())
+            .expected(correct.display_triplet())]);
+        }
+
+        Ok(vec![])
+    }
 
-    if axis != correct {
-        return Ok(vec![format!(
-            "File has incorrect Coordinate Axis. Expected [{}] actual [{}]. [{:?}]",
-            correct.display_triplet(),
+    fn fix(&self, doc: &Document, tree: &mut Node) -> anyhow::Result<Vec<String>> {
+        let axis = get_coordinate_axis(doc)
+            .ok_or_else(|| format_err!("Could not find coordinate axis."))?;
+        let correct = self.expected_axis(&get_application_name(doc));
+        if axis == *correct {
+            return Ok(vec![]);
+        }
+
+        let conversion = match AxisConversion::new(&axis, correct) {
+            Some(conversion) => conversion,
+            None => return Ok(vec![]),
+        };
+        if tree.child("GlobalSettings").is_none() {
+            return Ok(vec![]);
+        }
+
+        axis::convert(tree, &conversion);
+        let settings = tree
+            .child_mut("GlobalSettings")
+            .expect("GlobalSettings was found above");
+        for (name, v) in &[
+            ("UpAxis", correct.up),
+            ("FrontAxis", correct.front),
+            ("CoordAxis", correct.coord),
+        ] {
+            let (index, sign) = axis_index_and_sign(v);
+            settings.set_property(name, "int", "Integer", vec![AttributeValue::I32(index)]);
+            settings.set_property(
+                &format!("{}Sign", name),
+                "int",
+                "Integer",
+                vec![AttributeValue::I32(sign)],
+            );
+        }
+
+        Ok(vec![format!(
+            "Converted the coordinate axis from [{}] to [{}], rotating the scene to match.",
             axis.display_triplet(),
-            application_name,
-        )]);
+            correct.display_triplet()
+        )])
     }
+}
 
-    Ok(vec![])
+/// The axis system of a file. Each vector is a signed unit axis, ie. (0, 1, 0) for +Y.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "AxisNames")]
+pub struct CoordinateAxis {
+    pub up: Vector3<i8>,
+    pub front: Vector3<i8>,
+    pub coord: Vector3<i8>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct CoordinateAxis {
-    up: Vector3<i8>,
-    front: Vector3<i8>,
-    coord: Vector3<i8>,
+/// How a coordinate axis is written in the config: `{ up = "+Y", front = "+Z", coord = "+X" }`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AxisNames {
+    up: String,
+    front: String,
+    coord: String,
+}
+
+impl TryFrom<AxisNames> for CoordinateAxis {
+    type Error = anyhow::Error;
+
+    fn try_from(names: AxisNames) -> Result<Self, Self::Error> {
+        fn parse_axis(name: &str) -> anyhow::Result<Vector3<i8>> {
+            Ok(match name.trim() {
+                "+X" | "X" => Vector3::new(1, 0, 0),
+                "-X" => Vector3::new(-1, 0, 0),
+                "+Y" | "Y" => Vector3::new(0, 1, 0),
+                "-Y" => Vector3::new(0, -1, 0),
+                "+Z" | "Z" => Vector3::new(0, 0, 1),
+                "-Z" => Vector3::new(0, 0, -1),
+                _ => anyhow::bail!(
+                    "Invalid axis [{}]. Expected one of +X, -X, +Y, -Y, +Z, -Z.",
+                    name
+                ),
+            })
+        }
+
+        let axis = CoordinateAxis {
+            up: parse_axis(&names.up)?,
+            front: parse_axis(&names.front)?,
+            coord: parse_axis(&names.coord)?,
+        };
+
+        // The axes must be perpendicular to each other.
+        let dot = |a: Vector3<i8>, b: Vector3<i8>| a.x * b.x + a.y * b.y + a.z * b.z;
+        if dot(axis.up, axis.front) != 0
+            || dot(axis.up, axis.coord) != 0
+            || dot(axis.front, axis.coord) != 0
+        {
+            anyhow::bail!(
+                "Invalid coordinate axis [up={}, front={}, coord={}]. Each must be a different axis.",
+                names.up,
+                names.front,
+                names.coord
+            );
+        }
+
+        Ok(axis)
+    }
 }
 
 impl CoordinateAxis {
-    fn display_triplet(&self) -> String {
+    pub fn display_triplet(&self) -> String {
         fn axis_letter(v: &Vector3<i8>) -> String {
             match v {
                 Vector3 { x: 1, y: 0, z: 0 } => "+X".to_owned(),
@@ -95,7 +264,18 @@ fn coordinate_axis_for_software(application_name: &Option<ApplicationName>) -> C
     }
 }
 
-fn get_coordinate_axis(doc: &Document) -> Option<CoordinateAxis> {
+/// The inverse of `get_axis`: the axis index (0 = X, 1 = Y, 2 = Z) and sign of a unit axis.
+fn axis_index_and_sign(v: &Vector3<i8>) -> (i32, i32) {
+    if v.x != 0 {
+        (0, v.x as i32)
+    } else if v.y != 0 {
+        (1, v.y as i32)
+    } else {
+        (2, v.z as i32)
+    }
+}
+
+pub fn get_coordinate_axis(doc: &Document) -> Option<CoordinateAxis> {
     let global_settings = doc.global_settings()?;
 
     let up_axis = get_axis(&global_settings, "UpAxis")?;
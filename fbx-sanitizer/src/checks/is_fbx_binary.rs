@@ -1,12 +1,59 @@
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
+use crate::tree::Node;
 use content_inspector::ContentType::BINARY;
+use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
-/// Blender cannot load the FBX ASCII format. Reject files if they are not in the binary format.
+/// Returns true if the file is in the binary format.
 pub fn verify(path: &Path) -> anyhow::Result<bool> {
     let mut bytes = Vec::<u8>::new();
     File::open(path)?.read_to_end(&mut bytes)?;
     let t = content_inspector::inspect(&bytes);
     Ok(t == BINARY)
 }
+
+/// Blender cannot load the FBX ASCII format. Reject files if they are not in the binary format.
+///
+/// ASCII files are still checked by every other check. `--fix` (or the `convert` command) writes
+/// them as binary.
+#[derive(Default, Deserialize)]
+pub struct IsFbxBinary;
+
+impl Check for IsFbxBinary {
+    fn id(&self) -> &'static str {
+        "is_fbx_binary"
+    }
+
+    fn title(&self) -> &'static str {
+        "ASCII Format"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies the file is saved in the FBX Binary format. Blender can't open ASCII files."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "Blender cannot load the FBX ASCII format, so files must be saved in the binary format.\n\n\
+         ASCII files are still checked by every other check. --fix (or the convert command) \
+         writes them as binary."
+    }
+
+    fn run_on_file(&self, path: &Path) -> anyhow::Result<Vec<Diagnostic>> {
+        if !verify(path)? {
+            return Ok(vec![Diagnostic::new(
+                "File is not saved in FBX binary format.",
+            )]);
+        }
+
+        Ok(vec![])
+    }
+
+    fn fix(&self, _doc: &Document, _tree: &mut Node) -> anyhow::Result<Vec<String>> {
+        // Fixed files are always written as binary, so there is nothing to edit.
+        Ok(vec!["Converted the file from ASCII to binary.".to_owned()])
+    }
+}
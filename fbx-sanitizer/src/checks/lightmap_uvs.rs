@@ -0,0 +1,256 @@
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
+use crate::utils::{child_string, geo_name, validate_uv_set};
+use fbxcel_dom::fbxcel::tree::v7400::NodeHandle;
+use fbxcel_dom::v7400::object::geometry::TypedGeometryHandle;
+use fbxcel_dom::v7400::object::TypedObjectHandle;
+use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
+
+/// Verifies the UV set used for lightmaps. Unity bakes lightmaps into a second UV set (UV1), and
+/// each part of a mesh needs its own space in it: overlapping UVs share the same texels of the
+/// lightmap, and bleed light and shadow onto each other. UVs outside of 0-1 and UV triangles
+/// without any area don't get any texels at all.
+///
+/// Overlap is found by rasterizing the UV triangles into a grid of texels, and counting the texels
+/// that are covered more than once. Meshes without the UV set are skipped, since Unity can
+/// generate lightmap UVs for them. See `meshes_have_uvs` to require it.
+///
+/// This is only necessary for light baking, so it is opt-in.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightmapUvs {
+    /// The index of the UV set to check. Unity uses UV1 for lightmaps.
+    pub uv_set: i32,
+    /// The size of the grid the UVs are rasterized into, in texels on each side.
+    pub resolution: usize,
+    /// Meshes fail if more than this percentage of the texels they cover are covered more than
+    /// once. A little overlap is allowed, for texels on the edges of triangles.
+    pub max_overlap_percent: f64,
+    /// UV triangles with a smaller area than this (in 0-1 UV space) have no area.
+    pub zero_area_epsilon: f64,
+}
+
+impl Default for LightmapUvs {
+    fn default() -> Self {
+        LightmapUvs {
+            uv_set: 1,
+            resolution: 512,
+            max_overlap_percent: 0.1,
+            zero_area_epsilon: 1e-12,
+        }
+    }
+}
+
+impl Check for LightmapUvs {
+    fn id(&self) -> &'static str {
+        "lightmap_uvs"
+    }
+
+    fn title(&self) -> &'static str {
+        "Invalid lightmap UVs"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies the lightmap UVs don't overlap, stay within 0-1, and have no zero-area triangles. Bad lightmap UVs cause light and shadows to bleed in baked lighting."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "Unity bakes lightmaps into a second UV set (UV1), and each part of a mesh needs its own \
+         space in it. Overlapping UVs share the same texels of the lightmap, and bleed light and \
+         shadow onto each other. UVs outside of 0-1 and UV triangles without any area don't get \
+         any texels at all.\n\n\
+         Meshes without the UV set are skipped, since Unity can generate lightmap UVs for them. \
+         This is only necessary for light baking, so it is opt-in."
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+
+        for obj in doc.objects() {
+            if let TypedObjectHandle::Geometry(geo) = obj.get_typed() {
+                if let TypedGeometryHandle::Mesh(m) = geo {
+                    let mesh_name = geo_name(&geo).unwrap_or("No Name");
+                    let uv_node = obj.node().children_by_name("LayerElementUV").find(|n| {
+                        n.attributes().get(0).and_then(|a| a.get_i32()) == Some(self.uv_set)
+                    });
+                    let uv_node = match uv_node {
+                        Some(uv_node) => uv_node,
+                        None => continue,
+                    };
+
+                    // Invalid UV sets are reported by `meshes_have_uvs`.
+                    let polygon_vertices = m.polygon_vertices()?;
+                    let indices = polygon_vertices.raw_polygon_vertices();
+                    let control_point_count = polygon_vertices.raw_control_points()?.count();
+                    if validate_uv_set(uv_node, indices.len(), control_point_count).is_err() {
+                        continue;
+                    }
+                    let triangles = uv_triangles(uv_node, indices);
+
+                    let outside = triangles
+                        .iter()
+                        .flatten()
+                        .filter(|uv| uv.iter().any(|&x| !(0.0..=1.0).contains(&x)))
+                        .count();
+                    if outside > 0 {
+                        errors.push(
+                            Diagnostic::new(format!(
+                                "The lightmap UVs (UV{}) of the mesh [{}] are outside of 0-1 at {} triangle corners. They must be within 0-1.",
+                                self.uv_set, mesh_name, outside
+                            ))
+                            .with_object(&obj, mesh_name)
+                            .measured(outside)
+                            .expected(0),
+                        );
+                    }
+
+                    let zero_area = triangles
+                        .iter()
+                        .filter(|t| area(t).abs() < self.zero_area_epsilon)
+                        .count();
+                    if zero_area > 0 {
+                        errors.push(
+                            Diagnostic::new(format!(
+                                "The lightmap UVs (UV{}) of the mesh [{}] have {} triangles with no area.",
+                                self.uv_set, mesh_name, zero_area
+                            ))
+                            .with_object(&obj, mesh_name)
+                            .measured(zero_area)
+                            .expected(0),
+                        );
+                    }
+
+                    let (covered, overlapped) = rasterize(&triangles, self.resolution);
+                    if covered > 0 {
+                        let overlap = overlapped as f64 / covered as f64 * 100.0;
+                        if overlap > self.max_overlap_percent {
+                            errors.push(
+                                Diagnostic::new(format!(
+                                    "The lightmap UVs (UV{}) of the mesh [{}] overlap. {:.1}% of the UV area is used more than once.",
+                                    self.uv_set, mesh_name, overlap
+                                ))
+                                .with_object(&obj, mesh_name)
+                                .measured(format!("{:.1}%", overlap))
+                                .expected(format!("<= {}%", self.max_overlap_percent)),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+}
+
+type Uv = [f64; 2];
+
+/// Splits each polygon into a fan of triangles, and looks up the UV of each corner. The UV set
+/// must be valid (see `validate_uv_set`).
+fn uv_triangles(uv_node: NodeHandle<'_>, indices: &[i32]) -> Vec<[Uv; 3]> {
+    let array = |name: &str| {
+        uv_node
+            .children_by_name(name)
+            .next()
+            .and_then(|n| n.attributes().get(0))
+    };
+    let uvs = array("UV")
+        .and_then(|a| a.get_arr_f64())
+        .unwrap_or_default();
+    let uv_indices = array("UVIndex")
+        .and_then(|a| a.get_arr_i32())
+        .filter(|_| child_string(uv_node, "ReferenceInformationType") != Some("Direct"));
+    let by_polygon_vertex =
+        child_string(uv_node, "MappingInformationType") == Some("ByPolygonVertex");
+
+    // The UV of a corner of a polygon, by its index in the polygon vertices.
+    let uv = |polygon_vertex: usize| -> Option<Uv> {
+        let mut index = if by_polygon_vertex {
+            polygon_vertex
+        } else {
+            let i = indices[polygon_vertex];
+            (if i < 0 { !i } else { i }) as usize
+        };
+        if let Some(uv_indices) = uv_indices {
+            index = *uv_indices.get(index)? as usize;
+        }
+        Some([*uvs.get(index * 2)?, *uvs.get(index * 2 + 1)?])
+    };
+
+    let mut triangles = vec![];
+    let mut start = 0;
+    for (end, &index) in indices.iter().enumerate() {
+        // The last index of each polygon is negative.
+        if index >= 0 {
+            continue;
+        }
+        for corner in start + 1..end {
+            if let (Some(a), Some(b), Some(c)) = (uv(start), uv(corner), uv(corner + 1)) {
+                triangles.push([a, b, c]);
+            }
+        }
+        start = end + 1;
+    }
+    triangles
+}
+
+/// The signed area of a triangle. Positive if its corners are counter-clockwise.
+fn area([a, b, c]: &[Uv; 3]) -> f64 {
+    ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])) / 2.0
+}
+
+/// Rasterizes triangles into a grid of `resolution` by `resolution` texels, covering 0-1.
+/// Returns the number of texels covered by a triangle, and the number covered more than once.
+fn rasterize(triangles: &[[Uv; 3]], resolution: usize) -> (usize, usize) {
+    let mut coverage = vec![0u8; resolution * resolution];
+    let size = resolution as f64;
+
+    for triangle in triangles {
+        let [a, mut b, mut c] = *triangle;
+        let signed_area = area(triangle);
+        if signed_area == 0.0 {
+            continue;
+        }
+        if signed_area < 0.0 {
+            std::mem::swap(&mut b, &mut c);
+        }
+
+        // The texels whose centers are within the triangle's bounds.
+        let texels = |axis: usize| {
+            let min = a[axis].min(b[axis]).min(c[axis]) * size - 0.5;
+            let max = a[axis].max(b[axis]).max(c[axis]) * size - 0.5;
+            let first = min.ceil().max(0.0) as usize;
+            let last = (max.floor() + 1.0).min(size).max(0.0) as usize;
+            first..last
+        };
+        let columns = texels(0);
+        for y in texels(1) {
+            for x in columns.clone() {
+                let p = [(x as f64 + 0.5) / size, (y as f64 + 0.5) / size];
+                if covers(a, b, p) && covers(b, c, p) && covers(c, a, p) {
+                    let texel = &mut coverage[y * resolution + x];
+                    *texel = texel.saturating_add(1);
+                }
+            }
+        }
+    }
+
+    let covered = coverage.iter().filter(|&&n| n > 0).count();
+    let overlapped = coverage.iter().filter(|&&n| n > 1).count();
+    (covered, overlapped)
+}
+
+/// True if `p` is on the inner side of the edge from `a` to `b`, of a counter-clockwise triangle.
+///
+/// Points exactly on the edge are only covered by one of the two triangles sharing it, so shared
+/// edges aren't counted as overlap.
+fn covers(a: Uv, b: Uv, p: Uv) -> bool {
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let w = d[0] * (p[1] - a[1]) - d[1] * (p[0] - a[0]);
+    w > 0.0 || (w == 0.0 && (d[1] < 0.0 || (d[1] == 0.0 && d[0] > 0.0)))
+}
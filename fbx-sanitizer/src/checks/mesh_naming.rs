@@ -1,35 +1,74 @@
+use crate::checks::Check;
+use crate::config::deserialize_regexes;
+use crate::diagnostic::{Diagnostic, Severity};
 use fbxcel_dom::v7400::object::TypedObjectHandle;
 use fbxcel_dom::v7400::Document;
-use lazy_static::lazy_static;
 use regex::Regex;
-
-// Invalid mesh names:
-lazy_static! {
-    static ref CUBE: Regex = Regex::new(r"^Cube\.\d+$").unwrap();
-    static ref CYLINDER: Regex = Regex::new(r"^cylinder\d+$").unwrap();
-    static ref CYLINDER2: Regex = Regex::new(r"^Cylinder$").unwrap();
-}
+use serde::Deserialize;
 
 /// Checks for lazy mesh names like "Cube.124" and "cylinder16". They make it incredibly difficult
 /// to find objects in the hierarchy.
-/// Note: This should probably not be a strict check, and might be disabled. It's not a blocking issue.
-#[allow(dead_code)]
-pub fn verify(doc: &Document) -> anyhow::Result<Vec<String>> {
-    let mut errors: Vec<String> = vec![];
-    for o in doc.objects() {
-        if let TypedObjectHandle::Model(model) = o.get_typed() {
-            let name = if let Some(n) = model.name() {
-                n
-            } else {
-                errors.push("Model has no name".into());
-                continue;
-            };
-
-            if CUBE.is_match(name) || CYLINDER.is_match(name) || CYLINDER2.is_match(name) {
-                errors.push(format!("The model [{}] has a default name. Please name it something more specific, so it can be found easily in the Unity Editor.", name))
-            }
+/// This is not a blocking issue, so it is only reported as a warning.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshNaming {
+    /// Invalid mesh names. A model matching any of these has a default name.
+    #[serde(deserialize_with = "deserialize_regexes")]
+    pub patterns: Vec<Regex>,
+}
+
+impl Default for MeshNaming {
+    fn default() -> Self {
+        MeshNaming {
+            patterns: vec![
+                Regex::new(r"^Cube\.\d+$").unwrap(),
+                Regex::new(r"^cylinder\d+$").unwrap(),
+                Regex::new(r"^Cylinder$").unwrap(),
+            ],
         }
     }
+}
 
-    Ok(errors)
+impl Check for MeshNaming {
+    fn id(&self) -> &'static str {
+        "mesh_naming"
+    }
+
+    fn title(&self) -> &'static str {
+        "Bad mesh naming"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checks for default mesh names like \"Cube.124\" and \"cylinder16\", which make objects hard to find in the hierarchy."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "Lazy mesh names like \"Cube.124\" and \"cylinder16\" make it incredibly difficult to \
+         find objects in the hierarchy.\n\n\
+         This is not a blocking issue, so it is only reported as a warning."
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+        for o in doc.objects() {
+            if let TypedObjectHandle::Model(model) = o.get_typed() {
+                let name = if let Some(n) = model.name() {
+                    n
+                } else {
+                    errors.push(Diagnostic::new("Model has no name").with_object(&o, ""));
+                    continue;
+                };
+
+                if self.patterns.iter().any(|p| p.is_match(name)) {
+                    errors.push(Diagnostic::new(format!("The model [{}] has a default name. Please name it something more specific, so it can be found easily in the Unity Editor.", name)).with_object(&o, name))
+                }
+            }
+        }
+
+        Ok(errors)
+    }
 }
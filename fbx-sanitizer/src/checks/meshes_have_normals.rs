@@ -1,77 +1,370 @@
-use crate::utils::geo_name;
+use crate::checks::Check;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::utils::{child_string, geo_name, polygon_normal};
+use cgmath::{InnerSpace, Vector3, Zero};
+use fbxcel_dom::fbxcel::tree::v7400::NodeHandle;
 use fbxcel_dom::v7400::data::mesh::layer::TypedLayerElementHandle;
 use fbxcel_dom::v7400::object::geometry::TypedGeometryHandle;
 use fbxcel_dom::v7400::object::TypedObjectHandle;
 use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
+
+/// The number of polygon indices listed for each mesh.
+const LISTED_POLYGONS: usize = 5;
 
 /// Checks to make sure the object has normals. If it does not, Unity will generate its own normals
 /// based on the angle of each edge. Usually this is a terrible way to generate normals. Normals
 /// should be generated in the modeling program before export.
-/// 
-/// This check also verifies that the normals found contain the correct number of normals (one for 
-/// each vertex). If this is wrong, it will throw errors in Unity on import.
-pub fn verify(doc: &Document) -> anyhow::Result<Vec<String>> {
-    let mut errors = vec![];
-
-    for obj in doc.objects() {
-        if let TypedObjectHandle::Geometry(geo) = obj.get_typed() {
-            if let TypedGeometryHandle::Mesh(m) = geo {
-                let mesh_name = geo_name(&geo).unwrap_or("No Name");
-                let mut found_normals = false;
-
-                // Check for normals and validate their data
-                for layer_elem in m.layers().flat_map(|l| l.layer_element_entries()) {
-                    if let TypedLayerElementHandle::Normal(_normals) = layer_elem.typed_layer_element()? {
-                        found_normals = true;
-
-                        // Validate normal data consistency using low-level node API
-                        let geo_node = obj.node();
-                        if let Some(normal_node) = geo_node.children_by_name("LayerElementNormal").next() {
-                            // Get the normal count from the Normals array
-                            if let Some(normals_array_node) = normal_node.children_by_name("Normals").next() {
-                                if let Some(normals_attr) = normals_array_node.attributes().get(0) {
-                                    if let Some(normal_values) = normals_attr.get_arr_f64() {
-                                        let normal_count = normal_values.len() / 3; // 3 components per normal
-
-                                        // Get vertex count from the Vertices array
-                                        if let Some(vertices_node) = geo_node.children_by_name("Vertices").next() {
-                                            if let Some(vertices_attr) = vertices_node.attributes().get(0) {
-                                                if let Some(vertex_values) = vertices_attr.get_arr_f64() {
-                                                    let vertex_count = vertex_values.len() / 3; // 3 components per vertex
-
-                                                    // Check mapping mode
-                                                    if let Some(mapping_node) = normal_node.children_by_name("MappingInformationType").next() {
-                                                        if let Some(mapping_value) = mapping_node.attributes().iter().find_map(|attr| attr.get_string()) {
-                                                            // For ByVertice (per-vertex) normals, count should match vertex count
-                                                            if mapping_value == "ByVertice" {
-                                                                if normal_count != vertex_count {
-                                                                    errors.push(format!(
-                                                                        "The mesh [{}] has invalid normals: {} normal vectors but {} vertices. For MappingInformationType \"ByVertice\", these counts must match.",
-                                                                        mesh_name, normal_count, vertex_count
-                                                                    ));
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+///
+/// This check also verifies that the normals found contain the correct number of normals for their
+/// mapping mode (one for each vertex, polygon vertex or polygon). If this is wrong, it will throw
+/// errors in Unity on import.
+///
+/// The normals themselves are checked too. Zero-length normals are errors, as they can't be lit.
+/// Normals that aren't unit length, and faces whose normals point away from the face (as given by
+/// the winding of its vertices), are warnings. The latter are usually flipped faces, which are
+/// invisible from the front in Unity.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshesHaveNormals {
+    /// Normals whose length is further than this from 1 are not unit length.
+    pub unit_epsilon: f64,
+    /// Normals shorter than this have zero length.
+    pub zero_epsilon: f64,
+}
+
+impl Default for MeshesHaveNormals {
+    fn default() -> Self {
+        MeshesHaveNormals {
+            unit_epsilon: 0.001,
+            zero_epsilon: 0.000001,
+        }
+    }
+}
+
+impl Check for MeshesHaveNormals {
+    fn id(&self) -> &'static str {
+        "meshes_have_normals"
+    }
+
+    fn title(&self) -> &'static str {
+        "No normals"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies all meshes contain normals. Unity's 'calculate normals' is not great -- it's much better to use your modeling program."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "If a mesh has no normals, Unity will generate its own normals based on the angle of each \
+         edge. Usually this is a terrible way to generate normals. Normals should be generated in \
+         the modeling program before export.\n\n\
+         Normals must also have the right count for their mapping mode, or Unity throws errors on \
+         import. Zero-length normals can't be lit. Normals that aren't unit length light the mesh \
+         too brightly or darkly, and faces whose normals point away from the face are usually \
+         flipped, which makes them invisible from the front."
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+
+        for obj in doc.objects() {
+            if let TypedObjectHandle::Geometry(geo) = obj.get_typed() {
+                if let TypedGeometryHandle::Mesh(m) = geo {
+                    let mesh_name = geo_name(&geo).unwrap_or("No Name");
+                    let mut found_normals = false;
+
+                    for layer_elem in m.layers().flat_map(|l| l.layer_element_entries()) {
+                        if let TypedLayerElementHandle::Normal(_normals) =
+                            layer_elem.typed_layer_element()?
+                        {
+                            found_normals = true;
+                        }
+                    }
+
+                    if !found_normals {
+                        errors.push(Diagnostic::new(format!(
+                            "The mesh [{}] does not have vertex normals. Unity will generate bad normals, instead.",
+                            mesh_name
+                        ))
+                        .with_object(&obj, mesh_name));
+                        continue;
+                    }
+
+                    // Validate normal data consistency using low-level node API
+                    let polygon_vertices = m.polygon_vertices()?;
+                    let points: Vec<Vector3<f64>> = polygon_vertices
+                        .raw_control_points()?
+                        .map(|p| Vector3::new(p.x, p.y, p.z))
+                        .collect();
+                    let indices = polygon_vertices.raw_polygon_vertices();
+                    for normal_node in obj.node().children_by_name("LayerElementNormal") {
+                        let normals = match read_normals(normal_node, indices, points.len()) {
+                            Ok(normals) => normals,
+                            Err(invalid) => {
+                                errors.push(
+                                    invalid
+                                        .into_diagnostic(mesh_name)
+                                        .with_object(&obj, mesh_name),
+                                );
+                                continue;
                             }
+                        };
+
+                        let lengths: Vec<f64> =
+                            normals.vectors.iter().map(|n| n.magnitude()).collect();
+                        let zero = lengths.iter().filter(|&&l| l < self.zero_epsilon).count();
+                        if zero > 0 {
+                            errors.push(Diagnostic::new(format!(
+                                "The mesh [{}] has {} normals with zero length. These can't be lit.",
+                                mesh_name, zero
+                            ))
+                            .with_object(&obj, mesh_name)
+                            .measured(zero)
+                            .expected(0));
+                        }
+                        let not_unit = lengths
+                            .iter()
+                            .filter(|&&l| {
+                                l >= self.zero_epsilon && (l - 1.0).abs() > self.unit_epsilon
+                            })
+                            .count();
+                        if not_unit > 0 {
+                            errors.push(Diagnostic::new(format!(
+                                "The mesh [{}] has {} normals that are not unit length. Lighting will be too bright or dark where they aren't normalized.",
+                                mesh_name, not_unit
+                            ))
+                            .with_object(&obj, mesh_name)
+                            .with_severity(Severity::Warning)
+                            .measured(not_unit)
+                            .expected(0));
+                        }
+
+                        let flipped = flipped_faces(&points, indices, &normals);
+                        if !flipped.is_empty() {
+                            let listed: Vec<String> = flipped
+                                .iter()
+                                .take(LISTED_POLYGONS)
+                                .map(|p| p.to_string())
+                                .collect();
+                            errors.push(Diagnostic::new(format!(
+                                "The mesh [{}] has {} faces whose normals point away from the face, ie. polygons {}{}. These faces may be flipped.",
+                                mesh_name,
+                                flipped.len(),
+                                listed.join(", "),
+                                if flipped.len() > listed.len() { ", ..." } else { "" }
+                            ))
+                            .with_object(&obj, mesh_name)
+                            .with_severity(Severity::Warning)
+                            .measured(flipped.len())
+                            .expected(0));
                         }
                     }
                 }
+            }
+        }
 
-                if !found_normals {
-                    errors.push(format!(
-                        "The mesh [{}] does not have vertex normals. Unity will generate bad normals, instead.",
-                        mesh_name
-                    ));
-                }
+        Ok(errors)
+    }
+}
+
+/// The normals of a `LayerElementNormal`, and which one each polygon vertex uses.
+struct Normals {
+    vectors: Vec<Vector3<f64>>,
+    /// The index in `vectors` of the normal of each polygon vertex.
+    polygon_vertex_normals: Vec<usize>,
+}
+
+/// Why the normals of a mesh can't be used.
+enum InvalidNormals {
+    /// There are more or fewer normals (or normal indices) than the mapping mode needs.
+    Count {
+        mapping: String,
+        found: usize,
+        expected: usize,
+        /// What there must be one normal for, ie. "vertices".
+        per: &'static str,
+    },
+    Other(String),
+}
+
+impl InvalidNormals {
+    fn into_diagnostic(self, mesh_name: &str) -> Diagnostic {
+        match self {
+            InvalidNormals::Count {
+                mapping,
+                found,
+                expected,
+                per,
+            } => Diagnostic::new(format!(
+                "The mesh [{}] has invalid normals: {} normal vectors but {} {}. For MappingInformationType \"{}\", these counts must match.",
+                mesh_name, found, expected, per, mapping
+            ))
+            .measured(found)
+            .expected(expected),
+            InvalidNormals::Other(reason) => Diagnostic::new(format!(
+                "The mesh [{}] has invalid normals: {}",
+                mesh_name, reason
+            )),
+        }
+    }
+}
+
+/// Reads the normals of a `LayerElementNormal`, in any of its mapping and reference modes, and
+/// checks there are the right number of them for the mesh.
+fn read_normals(
+    normal_node: NodeHandle<'_>,
+    indices: &[i32],
+    control_point_count: usize,
+) -> Result<Normals, InvalidNormals> {
+    let array = |name: &str| {
+        normal_node
+            .children_by_name(name)
+            .next()
+            .and_then(|n| n.attributes().get(0))
+    };
+    let values = array("Normals")
+        .and_then(|a| a.get_arr_f64())
+        .ok_or_else(|| InvalidNormals::Other("there is no normal data.".to_owned()))?;
+    let vectors: Vec<Vector3<f64>> = values
+        .chunks_exact(3)
+        .map(|n| Vector3::new(n[0], n[1], n[2]))
+        .collect();
+
+    // The polygon and control point of each polygon vertex.
+    let mut polygon = 0;
+    let mut corners = Vec::with_capacity(indices.len());
+    for &index in indices {
+        corners.push((polygon, (if index < 0 { !index } else { index }) as usize));
+        // The last index of each polygon is negative.
+        if index < 0 {
+            polygon += 1;
+        }
+    }
+
+    let mapping = child_string(normal_node, "MappingInformationType").unwrap_or("(none)");
+    let (expected, per) = match mapping {
+        "ByPolygonVertex" => (indices.len(), "polygon vertices"),
+        "ByVertice" | "ByVertex" | "ByControlPoint" => (control_point_count, "vertices"),
+        "ByPolygon" => (polygon, "polygons"),
+        "AllSame" => (1, "mesh"),
+        _ => {
+            return Err(InvalidNormals::Other(format!(
+                "MappingInformationType \"{}\" is not supported.",
+                mapping
+            )))
+        }
+    };
+    // The normal (or normal index) of each polygon vertex, by the mapping mode.
+    let keys: Vec<usize> = corners
+        .iter()
+        .enumerate()
+        .map(
+            |(polygon_vertex, &(polygon, control_point))| match mapping {
+                "ByPolygonVertex" => polygon_vertex,
+                "ByPolygon" => polygon,
+                "AllSame" => 0,
+                _ => control_point,
+            },
+        )
+        .collect();
+
+    let reference = child_string(normal_node, "ReferenceInformationType").unwrap_or("(none)");
+    let polygon_vertex_normals = match reference {
+        "Direct" => {
+            if vectors.len() != expected {
+                return Err(InvalidNormals::Count {
+                    mapping: mapping.to_owned(),
+                    found: vectors.len(),
+                    expected,
+                    per,
+                });
+            }
+            keys
+        }
+        // "Index" is the old name of "IndexToDirect".
+        "IndexToDirect" | "Index" => {
+            let normal_indices = array("NormalsIndex")
+                .and_then(|a| a.get_arr_i32())
+                .ok_or_else(|| InvalidNormals::Other("there are no normal indices.".to_owned()))?;
+            if normal_indices.len() != expected {
+                return Err(InvalidNormals::Count {
+                    mapping: mapping.to_owned(),
+                    found: normal_indices.len(),
+                    expected,
+                    per,
+                });
+            }
+            if normal_indices
+                .iter()
+                .any(|&i| i < 0 || i as usize >= vectors.len())
+            {
+                return Err(InvalidNormals::Other(format!(
+                    "some normal indices are out of range. There are {} normals.",
+                    vectors.len()
+                )));
             }
+            // Keys are out of range for meshes with out of range vertex indices, or an
+            // unfinished last polygon.
+            keys.iter()
+                .map(|&key| normal_indices.get(key).map(|&i| i as usize))
+                .collect::<Option<Vec<usize>>>()
+                .ok_or_else(missing_normals)?
         }
+        _ => {
+            return Err(InvalidNormals::Other(format!(
+                "ReferenceInformationType \"{}\" is not supported.",
+                reference
+            )))
+        }
+    };
+
+    if polygon_vertex_normals.iter().any(|&i| i >= vectors.len()) {
+        return Err(missing_normals());
     }
+    Ok(Normals {
+        vectors,
+        polygon_vertex_normals,
+    })
+}
 
-    Ok(errors)
+/// Some polygon vertices don't have a normal, because the mesh itself is broken.
+fn missing_normals() -> InvalidNormals {
+    InvalidNormals::Other(
+        "some polygon vertices have no normal. The mesh has out of range vertex indices, or an unfinished polygon."
+            .to_owned(),
+    )
+}
+
+/// Finds the polygons whose normals point away from the face. The face's own normal comes from
+/// the winding of its vertices (see `polygon_normal`), and is compared to the sum of the normals
+/// of its corners.
+fn flipped_faces(points: &[Vector3<f64>], indices: &[i32], normals: &Normals) -> Vec<usize> {
+    let mut flipped = vec![];
+    let mut start = 0;
+    let mut polygon = 0;
+    for (end, &index) in indices.iter().enumerate() {
+        // The last index of each polygon is negative.
+        if index >= 0 {
+            continue;
+        }
+
+        let corners: Option<Vec<Vector3<f64>>> = indices[start..=end]
+            .iter()
+            .map(|&i| points.get((if i < 0 { !i } else { i }) as usize).copied())
+            .collect();
+        if let Some(corners) = corners {
+            let face = polygon_normal(&corners);
+            let normal = (start..=end)
+                .map(|pv| normals.vectors[normals.polygon_vertex_normals[pv]])
+                .fold(Vector3::zero(), |sum, n| sum + n);
+            if face.dot(normal) < 0.0 {
+                flipped.push(polygon);
+            }
+        }
+
+        start = end + 1;
+        polygon += 1;
+    }
+    flipped
 }
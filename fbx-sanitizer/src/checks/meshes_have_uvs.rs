@@ -0,0 +1,103 @@
+use crate::checks::Check;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::utils::{geo_name, uv_set_name, validate_uv_set};
+use fbxcel_dom::v7400::data::mesh::layer::TypedLayerElementHandle;
+use fbxcel_dom::v7400::object::geometry::TypedGeometryHandle;
+use fbxcel_dom::v7400::object::TypedObjectHandle;
+use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
+
+/// Checks that meshes have enough UV sets, and that each UV set is valid. A mesh without UVs
+/// can't be textured, and lightmapping in Unity needs a second UV set (UV1), unless Unity
+/// generates one.
+///
+/// Missing UV sets are reported as warnings, since untextured meshes (ie. colliders) don't need
+/// them. UV sets that don't match the mesh are errors, as Unity throws them away on import.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshesHaveUvs {
+    /// Meshes with fewer UV sets than this are reported. Set this to 2 if lightmap UVs are
+    /// authored in the modeling program.
+    pub min_uv_sets: usize,
+}
+
+impl Default for MeshesHaveUvs {
+    fn default() -> Self {
+        MeshesHaveUvs { min_uv_sets: 1 }
+    }
+}
+
+impl Check for MeshesHaveUvs {
+    fn id(&self) -> &'static str {
+        "meshes_have_uvs"
+    }
+
+    fn title(&self) -> &'static str {
+        "Missing or invalid UVs"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies all meshes have enough UV sets, and that each UV set matches the mesh. Textures and lightmaps need UVs."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "A mesh without UVs can't be textured, and lightmapping in Unity needs a second UV set \
+         (UV1), unless Unity generates one.\n\n\
+         Missing UV sets are reported as warnings, since untextured meshes (ie. colliders) don't \
+         need them. UV sets that don't match the mesh are errors, as Unity throws them away on \
+         import."
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+
+        for obj in doc.objects() {
+            if let TypedObjectHandle::Geometry(geo) = obj.get_typed() {
+                if let TypedGeometryHandle::Mesh(m) = geo {
+                    let mesh_name = geo_name(&geo).unwrap_or("No Name");
+
+                    let mut uv_sets = 0;
+                    for layer_elem in m.layers().flat_map(|l| l.layer_element_entries()) {
+                        if let TypedLayerElementHandle::Uv(_) = layer_elem.typed_layer_element()? {
+                            uv_sets += 1;
+                        }
+                    }
+                    if uv_sets < self.min_uv_sets {
+                        errors.push(
+                            Diagnostic::new(format!(
+                                "The mesh [{}] has {} UV sets, but needs at least {}.",
+                                mesh_name, uv_sets, self.min_uv_sets
+                            ))
+                            .with_object(&obj, mesh_name)
+                            .with_severity(Severity::Warning)
+                            .measured(uv_sets)
+                            .expected(format!(">= {}", self.min_uv_sets)),
+                        );
+                    }
+
+                    // Validate each UV set against the mesh, using the low-level node API.
+                    let polygon_vertices = m.polygon_vertices()?;
+                    let polygon_vertex_count = polygon_vertices.raw_polygon_vertices().len();
+                    let control_point_count = polygon_vertices.raw_control_points()?.count();
+                    for uv_node in obj.node().children_by_name("LayerElementUV") {
+                        if let Err(message) =
+                            validate_uv_set(uv_node, polygon_vertex_count, control_point_count)
+                        {
+                            errors.push(
+                                Diagnostic::new(format!(
+                                    "The mesh [{}] has an invalid UV set [{}]: {}",
+                                    mesh_name,
+                                    uv_set_name(uv_node),
+                                    message
+                                ))
+                                .with_object(&obj, mesh_name),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+}
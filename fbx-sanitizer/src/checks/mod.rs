@@ -1,10 +1,230 @@
+use crate::config::Config;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::tree::Node;
+use anyhow::{bail, format_err, Context};
+use fbxcel_dom::v7400::Document;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
 pub mod bounding_box_size;
 pub mod correct_coordinate_axis;
 pub mod is_fbx_binary;
+pub mod lightmap_uvs;
 pub mod mesh_naming;
 pub mod meshes_have_normals;
+pub mod meshes_have_uvs;
+pub mod no_degenerate_triangles;
+pub mod no_invalid_values;
+pub mod no_namespaces;
 pub mod no_quads;
+pub mod no_scale_compensation;
 pub mod root_has_identity_transform;
 pub mod units_are_in_meters;
-pub mod no_scale_compensation;
-pub mod no_namespaces;
+
+/// A single rule that an fbx file is verified against.
+///
+/// Checks are registered in [`CHECKS`], and can be listed, enabled and disabled from the
+/// command line or the config file by their id. A check's settings are the fields of the struct
+/// implementing it, which are deserialized from the check's table in the config.
+pub trait Check: Sync {
+    /// Stable identifier for the check. This is the same as the name of the check's module.
+    fn id(&self) -> &'static str;
+
+    /// Short, human readable title of the issue this check finds.
+    fn title(&self) -> &'static str;
+
+    /// One or two sentences explaining what the check verifies and why.
+    fn description(&self) -> &'static str;
+
+    /// The reasoning behind the check, in a few paragraphs: why the issue matters in Unity and
+    /// other tools, and what can be done about it. Shown as the help text of SARIF rules.
+    fn rationale(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// Opt-in checks return false here, and only run when enabled explicitly.
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    /// Runs on the file itself, before it is parsed. Most checks only need [`Check::run`].
+    fn run_on_file(&self, _path: &Path) -> anyhow::Result<Vec<Diagnostic>> {
+        Ok(vec![])
+    }
+
+    /// Runs on the parsed document.
+    fn run(&self, _doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        Ok(vec![])
+    }
+
+    /// Repairs the issues found by [`Check::run`] by editing `tree`, an editable copy of `doc`.
+    /// Only called with `--fix`, and only if the check found an issue.
+    ///
+    /// Returns a description of each change made. Issues that can't be fixed safely are left
+    /// alone, and are reported again when the fixed file is checked.
+    fn fix(&self, _doc: &Document, _tree: &mut Node) -> anyhow::Result<Vec<String>> {
+        Ok(vec![])
+    }
+}
+
+/// Builds a check from its settings table in the config. Checks without settings use their
+/// defaults.
+type Loader = fn(Option<&toml::value::Table>) -> anyhow::Result<Box<dyn Check>>;
+
+/// Every check known to the sanitizer, in the order they are run and reported.
+const CHECKS: &[Loader] = &[
+    load::<is_fbx_binary::IsFbxBinary>,
+    load::<units_are_in_meters::UnitsAreInMeters>,
+    load::<correct_coordinate_axis::CorrectCoordinateAxis>,
+    load::<root_has_identity_transform::RootHasIdentityTransform>,
+    load::<no_invalid_values::NoInvalidValues>,
+    load::<meshes_have_normals::MeshesHaveNormals>,
+    load::<meshes_have_uvs::MeshesHaveUvs>,
+    load::<no_scale_compensation::NoScaleCompensation>,
+    load::<no_namespaces::NoNamespaces>,
+    load::<bounding_box_size::BoundingBoxSize>,
+    load::<lightmap_uvs::LightmapUvs>,
+    load::<no_quads::NoQuads>,
+    load::<no_degenerate_triangles::NoDegenerateTriangles>,
+    load::<mesh_naming::MeshNaming>,
+];
+
+/// Settings shared by every check. These are removed from a check's table before the rest is
+/// deserialized into the check itself.
+const ENABLED_KEY: &str = "enabled";
+const SEVERITY_KEY: &str = "severity";
+
+fn load<C>(settings: Option<&toml::value::Table>) -> anyhow::Result<Box<dyn Check>>
+where
+    C: Check + Default + DeserializeOwned + 'static,
+{
+    let mut settings = settings.cloned().unwrap_or_default();
+    settings.remove(ENABLED_KEY);
+    settings.remove(SEVERITY_KEY);
+
+    if settings.is_empty() {
+        return Ok(Box::new(C::default()));
+    }
+
+    let id = C::default().id();
+    let check: C = toml::Value::Table(settings)
+        .try_into()
+        .with_context(|| format!("Invalid settings for check [{}]", id))?;
+    Ok(Box::new(check))
+}
+
+/// Every check known to the sanitizer, with default settings.
+pub fn all_checks() -> Vec<Box<dyn Check>> {
+    CHECKS
+        .iter()
+        .map(|load| load(None).expect("default settings are always valid"))
+        .collect()
+}
+
+/// A check whose severity was changed in the config.
+struct WithSeverity {
+    check: Box<dyn Check>,
+    severity: Severity,
+}
+
+impl Check for WithSeverity {
+    fn id(&self) -> &'static str {
+        self.check.id()
+    }
+
+    fn title(&self) -> &'static str {
+        self.check.title()
+    }
+
+    fn description(&self) -> &'static str {
+        self.check.description()
+    }
+
+    fn rationale(&self) -> &'static str {
+        self.check.rationale()
+    }
+
+    fn default_severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        self.check.enabled_by_default()
+    }
+
+    fn run_on_file(&self, path: &Path) -> anyhow::Result<Vec<Diagnostic>> {
+        self.check.run_on_file(path)
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        self.check.run(doc)
+    }
+
+    fn fix(&self, doc: &Document, tree: &mut Node) -> anyhow::Result<Vec<String>> {
+        self.check.fix(doc, tree)
+    }
+}
+
+/// Returns the checks that should run, configured with the settings from `config`.
+///
+/// A check runs if it is enabled by default or in the config, and the command line can override
+/// both with `enable` and `disable`. Fails if an id does not name a known check, or if the
+/// settings for a check are invalid.
+pub fn select_checks(
+    config: &Config,
+    enable: &[&str],
+    disable: &[&str],
+) -> anyhow::Result<Vec<Box<dyn Check>>> {
+    let known = all_checks();
+    for id in enable
+        .iter()
+        .chain(disable)
+        .copied()
+        .chain(config.checks.keys().map(String::as_str))
+    {
+        if !known.iter().any(|c| c.id() == id) {
+            bail!(
+                "Unknown check [{}]. Use --list-checks to see all available checks.",
+                id
+            );
+        }
+    }
+
+    let mut checks = vec![];
+    for (load, default) in CHECKS.iter().zip(known) {
+        let id = default.id();
+        let settings = config.check_settings(id)?;
+
+        let mut enabled = default.enabled_by_default();
+        if let Some(value) = settings.and_then(|s| s.get(ENABLED_KEY)) {
+            enabled = value
+                .as_bool()
+                .ok_or_else(|| format_err!("[{}] {} must be true or false.", id, ENABLED_KEY))?;
+        }
+        if enable.contains(&id) {
+            enabled = true;
+        }
+        if disable.contains(&id) {
+            enabled = false;
+        }
+        if !enabled {
+            continue;
+        }
+
+        let check = load(settings)?;
+        match settings.and_then(|s| s.get(SEVERITY_KEY)) {
+            None => checks.push(check),
+            Some(value) => {
+                let severity = value
+                    .as_str()
+                    .ok_or_else(|| format_err!("[{}] {} must be a string.", id, SEVERITY_KEY))?
+                    .parse()?;
+                checks.push(Box::new(WithSeverity { check, severity }));
+            }
+        }
+    }
+
+    Ok(checks)
+}
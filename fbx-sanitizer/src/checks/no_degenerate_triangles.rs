@@ -0,0 +1,171 @@
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
+use crate::utils::geo_name;
+use cgmath::{InnerSpace, MetricSpace, Point3};
+use fbxcel_dom::v7400::object::geometry::TypedGeometryHandle;
+use fbxcel_dom::v7400::object::TypedObjectHandle;
+use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
+
+/// The number of polygon indices listed for each mesh.
+const LISTED_POLYGONS: usize = 5;
+
+/// Checks for degenerate triangles: triangles that use the same vertex twice, have two vertices
+/// in the same place (a collapsed edge), or have no area. They can't be shaded, so they cause
+/// lighting seams, and Unity calculates NaN tangents for them.
+///
+/// Polygons larger than triangles are checked as a fan of triangles.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoDegenerateTriangles {
+    /// Edges shorter than this, relative to the size (bounding box diagonal) of the mesh, are
+    /// collapsed. Triangles with an area smaller than this squared have no area.
+    pub epsilon: f64,
+}
+
+impl Default for NoDegenerateTriangles {
+    fn default() -> Self {
+        NoDegenerateTriangles { epsilon: 1e-6 }
+    }
+}
+
+/// The degenerate polygons of a mesh, counted by why they are degenerate.
+#[derive(Default)]
+struct Degenerate {
+    repeated_vertices: usize,
+    collapsed_edges: usize,
+    zero_area: usize,
+    /// The first few degenerate polygons, by index.
+    polygons: Vec<usize>,
+}
+
+impl Check for NoDegenerateTriangles {
+    fn id(&self) -> &'static str {
+        "no_degenerate_triangles"
+    }
+
+    fn title(&self) -> &'static str {
+        "Degenerate triangles"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies no triangle has repeated vertices, a collapsed edge, or no area. These cause lighting seams and NaN tangents in Unity."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "Triangles that use the same vertex twice, have two vertices in the same place (a \
+         collapsed edge), or have no area can't be shaded. They cause lighting seams, and Unity \
+         calculates NaN tangents for them.\n\n\
+         Polygons larger than triangles are checked as a fan of triangles."
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+
+        for obj in doc.objects() {
+            if let TypedObjectHandle::Geometry(geo) = obj.get_typed() {
+                if let TypedGeometryHandle::Mesh(m) = geo {
+                    let polygon_vertices = m.polygon_vertices()?;
+                    let points: Vec<Point3<f64>> = polygon_vertices
+                        .raw_control_points()?
+                        .map(|p| Point3::new(p.x, p.y, p.z))
+                        .collect();
+                    let degenerate =
+                        self.find_degenerate(&points, polygon_vertices.raw_polygon_vertices());
+
+                    let count = degenerate.repeated_vertices
+                        + degenerate.collapsed_edges
+                        + degenerate.zero_area;
+                    if count > 0 {
+                        let name = geo_name(&geo).unwrap_or("No Name");
+                        let polygons: Vec<String> =
+                            degenerate.polygons.iter().map(|p| p.to_string()).collect();
+                        errors.push(Diagnostic::new(format!(
+                            "The mesh [{}] has {} degenerate polygons: {} with repeated vertices, {} with collapsed edges, and {} with no area. \
+                            The first are polygons {}{}.",
+                            name,
+                            count,
+                            degenerate.repeated_vertices,
+                            degenerate.collapsed_edges,
+                            degenerate.zero_area,
+                            polygons.join(", "),
+                            if count > polygons.len() { ", ..." } else { "" }
+                        ))
+                        .with_object(&obj, name)
+                        .measured(count)
+                        .expected(0));
+                    }
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+}
+
+impl NoDegenerateTriangles {
+    /// Finds the degenerate polygons of a mesh. Each polygon is only counted once, for the first
+    /// reason found.
+    fn find_degenerate(&self, points: &[Point3<f64>], indices: &[i32]) -> Degenerate {
+        let mut degenerate = Degenerate::default();
+
+        // The tolerances scale with the size of the mesh. NaN and infinite vertices are reported by
+        // `no_invalid_values`, and are left out.
+        let finite = |p: &Point3<f64>| p.x.is_finite() && p.y.is_finite() && p.z.is_finite();
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for p in points.iter().filter(|p| finite(p)) {
+            min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        let min_length = self.epsilon * min.distance(max);
+        let min_area = min_length * min_length;
+
+        let mut polygon = vec![];
+        let mut polygon_index = 0;
+        for &index in indices {
+            // The last index of each polygon is negative.
+            polygon.push((if index < 0 { !index } else { index }) as usize);
+            if index >= 0 {
+                continue;
+            }
+
+            let corners: Option<Vec<Point3<f64>>> = polygon
+                .iter()
+                .map(|&i| points.get(i).copied().filter(finite))
+                .collect();
+            let found = match corners {
+                // Indices out of range and invalid vertices are not this check's concern.
+                None => false,
+                Some(corners) => {
+                    let repeated = (1..polygon.len()).any(|i| polygon[..i].contains(&polygon[i]));
+                    let collapsed = (0..corners.len()).any(|i| {
+                        corners[i].distance(corners[(i + 1) % corners.len()]) <= min_length
+                    });
+                    let zero_area = (1..corners.len().saturating_sub(1)).any(|i| {
+                        let edge1 = corners[i] - corners[0];
+                        let edge2 = corners[i + 1] - corners[0];
+                        edge1.cross(edge2).magnitude() / 2.0 <= min_area
+                    });
+
+                    if repeated {
+                        degenerate.repeated_vertices += 1;
+                    } else if collapsed {
+                        degenerate.collapsed_edges += 1;
+                    } else if zero_area {
+                        degenerate.zero_area += 1;
+                    }
+                    repeated || collapsed || zero_area
+                }
+            };
+            if found && degenerate.polygons.len() < LISTED_POLYGONS {
+                degenerate.polygons.push(polygon_index);
+            }
+
+            polygon.clear();
+            polygon_index += 1;
+        }
+
+        degenerate
+    }
+}
@@ -0,0 +1,264 @@
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
+use crate::utils::{geo_name, uv_set_name};
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use fbxcel_dom::fbxcel::tree::v7400::NodeHandle;
+use fbxcel_dom::v7400::object::{ObjectHandle, TypedObjectHandle};
+use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
+
+/// Checks for NaN, infinite and absurdly large numbers in vertices, normals, UVs, transforms, skin
+/// bind matrices and animation. These come from broken exporters or scripts, and make the mesh
+/// disappear (or the whole scene, in the case of a transform) in Unity.
+///
+/// Each corrupt channel is reported with the object it belongs to, so it can be found and fixed.
+/// Model properties other than the transform are only checked for NaN and infinite values.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoInvalidValues {
+    /// Values larger than this (in either direction) are reported. The limit is in raw file
+    /// values, for every channel, and isn't converted with the file's `UnitScaleFactor`. The
+    /// default is 100km in a file in centimeters, but 10,000km in a file in meters.
+    pub max_magnitude: f64,
+}
+
+impl Default for NoInvalidValues {
+    fn default() -> Self {
+        NoInvalidValues { max_magnitude: 1e7 }
+    }
+}
+
+/// The model properties that place it in the scene. Their values must be below `max_magnitude`.
+const TRANSFORM_PROPERTIES: &[&str] = &[
+    "Lcl Translation",
+    "Lcl Rotation",
+    "Lcl Scaling",
+    "PreRotation",
+    "PostRotation",
+    "RotationOffset",
+    "RotationPivot",
+    "ScalingOffset",
+    "ScalingPivot",
+    "GeometricTranslation",
+    "GeometricRotation",
+    "GeometricScaling",
+];
+
+/// The invalid values found in a channel, ie. the vertices of a mesh.
+#[derive(Default)]
+struct Invalid {
+    nan: usize,
+    infinite: usize,
+    huge: usize,
+    /// The first invalid value.
+    first: Option<f64>,
+}
+
+impl Check for NoInvalidValues {
+    fn id(&self) -> &'static str {
+        "no_invalid_values"
+    }
+
+    fn title(&self) -> &'static str {
+        "NaN or infinite values"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies vertices, normals, UVs, transforms and animation don't contain NaN, infinite or absurdly large values."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "NaN, infinite and absurdly large numbers come from broken exporters or scripts. In \
+         vertices, normals, UVs, skin bind matrices or animation, they make the mesh disappear in \
+         Unity. In a transform, they make the whole scene disappear.\n\n\
+         Each corrupt channel is reported with the object it belongs to, so it can be found and \
+         fixed."
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+
+        for obj in doc.objects() {
+            let node = obj.node();
+            match obj.get_typed() {
+                TypedObjectHandle::Geometry(geo) => {
+                    let name = geo_name(&geo).unwrap_or("No Name");
+                    let mut report = |channel: &str, invalid| {
+                        self.report(&mut errors, &obj, "mesh", name, channel, invalid)
+                    };
+                    report("vertices", self.scan_array(node, "Vertices"));
+                    for normals in node.children_by_name("LayerElementNormal") {
+                        report("normals", self.scan_array(normals, "Normals"));
+                    }
+                    for uvs in node.children_by_name("LayerElementUV") {
+                        let channel = format!("UVs [{}]", uv_set_name(uvs));
+                        report(&channel, self.scan_array(uvs, "UV"));
+                    }
+                }
+                TypedObjectHandle::Model(model) => {
+                    let name = model.name().unwrap_or("(no name)");
+                    // Transforms, and any other numeric properties. Only transforms have to be
+                    // in range: other properties, ie. user properties, can legitimately be huge.
+                    for property in node
+                        .children_by_name("Properties70")
+                        .flat_map(|p| p.children_by_name("P"))
+                    {
+                        let attributes = property.attributes();
+                        let property_name = match attributes.get(0).and_then(|a| a.get_string()) {
+                            Some(property_name) => property_name,
+                            None => continue,
+                        };
+                        let values = attributes.iter().skip(4).filter_map(|a| match a {
+                            AttributeValue::F64(v) => Some(*v),
+                            AttributeValue::F32(v) => Some(f64::from(*v)),
+                            _ => None,
+                        });
+                        let invalid = if TRANSFORM_PROPERTIES.contains(&property_name) {
+                            self.scan(values)
+                        } else {
+                            scan_within(values, f64::INFINITY)
+                        };
+                        self.report(&mut errors, &obj, "model", name, property_name, invalid);
+                    }
+                }
+                _ if obj.subclass() == "Cluster" => {
+                    let name = cluster_name(&obj);
+                    for (array, channel) in &[
+                        ("Transform", "bind matrix"),
+                        ("TransformLink", "bone bind matrix"),
+                        ("Weights", "weights"),
+                    ] {
+                        let invalid = self.scan_array(node, array);
+                        self.report(&mut errors, &obj, "skin cluster", name, channel, invalid);
+                    }
+                }
+                _ if obj.class() == "AnimCurve" => {
+                    let name = curve_name(&obj);
+                    let invalid = self.scan_array(node, "KeyValueFloat");
+                    self.report(&mut errors, &obj, "animation curve", &name, "keys", invalid);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(errors)
+    }
+}
+
+impl NoInvalidValues {
+    /// Counts the NaN, infinite and huge values.
+    fn scan(&self, values: impl Iterator<Item = f64>) -> Invalid {
+        scan_within(values, self.max_magnitude)
+    }
+
+    /// Scans the numbers in the array of the child `array` of `node`, if it has one.
+    fn scan_array(&self, node: NodeHandle<'_>, array: &str) -> Invalid {
+        let values = node
+            .children_by_name(array)
+            .next()
+            .and_then(|n| n.attributes().get(0));
+        match values {
+            Some(AttributeValue::ArrF64(values)) => self.scan(values.iter().copied()),
+            Some(AttributeValue::ArrF32(values)) => self.scan(values.iter().map(|&v| f64::from(v))),
+            _ => Invalid::default(),
+        }
+    }
+
+    fn report(
+        &self,
+        errors: &mut Vec<Diagnostic>,
+        obj: &ObjectHandle<'_>,
+        kind: &str,
+        name: &str,
+        channel: &str,
+        invalid: Invalid,
+    ) {
+        let first = match invalid.first {
+            Some(first) => first,
+            None => return,
+        };
+
+        let mut found = vec![];
+        if invalid.nan > 0 {
+            found.push(format!("{} NaN values", invalid.nan));
+        }
+        if invalid.infinite > 0 {
+            found.push(format!("{} infinite values", invalid.infinite));
+        }
+        if invalid.huge > 0 {
+            found.push(format!(
+                "{} values larger than {}",
+                invalid.huge, self.max_magnitude
+            ));
+        }
+        errors.push(
+            Diagnostic::new(format!(
+                "The {} of the {} [{}] contain {}.",
+                channel,
+                kind,
+                name,
+                found.join(", ")
+            ))
+            .with_object(obj, name)
+            .measured(first)
+            .expected(format!("a number between -{0} and {0}", self.max_magnitude)),
+        );
+    }
+}
+
+/// Counts the NaN and infinite values, and the values larger than `max_magnitude`.
+fn scan_within(values: impl Iterator<Item = f64>, max_magnitude: f64) -> Invalid {
+    let mut invalid = Invalid::default();
+    for value in values {
+        if value.is_nan() {
+            invalid.nan += 1;
+        } else if value.is_infinite() {
+            invalid.infinite += 1;
+        } else if value.abs() > max_magnitude {
+            invalid.huge += 1;
+        } else {
+            continue;
+        }
+        invalid.first = invalid.first.or(Some(value));
+    }
+    invalid
+}
+
+/// Skin clusters are usually named after their bone. Unnamed clusters are named by the bone they
+/// are connected to.
+fn cluster_name<'a>(cluster: &ObjectHandle<'a>) -> &'a str {
+    cluster
+        .name()
+        .filter(|name| !name.is_empty())
+        .or_else(|| {
+            cluster
+                .source_objects()
+                .filter_map(|o| o.object_handle())
+                .find(|o| o.class() == "Model")
+                .and_then(|o| o.name())
+        })
+        .unwrap_or("(no name)")
+}
+
+/// Animation curves don't have names, so they are named by what they animate, ie.
+/// "Crate Lcl Translation X".
+fn curve_name(curve: &ObjectHandle<'_>) -> String {
+    for curve_node in curve.destination_objects() {
+        let axis = curve_node.label().unwrap_or("").trim_start_matches("d|");
+        let targets = curve_node
+            .object_handle()
+            .into_iter()
+            .flat_map(|n| n.destination_objects());
+        for target in targets {
+            if let (Some(object), Some(property)) = (target.object_handle(), target.label()) {
+                return format!(
+                    "{} {} {}",
+                    object.name().unwrap_or("(no name)"),
+                    property,
+                    axis
+                );
+            }
+        }
+    }
+    "(unknown)".to_owned()
+}
@@ -1,11 +1,11 @@
+use crate::checks::Check;
+use crate::config::deserialize_regex;
+use crate::diagnostic::Diagnostic;
+use crate::tree::Node;
 use fbxcel_dom::v7400::object::TypedObjectHandle;
 use fbxcel_dom::v7400::Document;
-use lazy_static::lazy_static;
 use regex::Regex;
-
-lazy_static! {
-    static ref RE_NAMESPACE: Regex = Regex::new(r"^(.*):.*$").unwrap();
-}
+use serde::Deserialize;
 
 /// Depending on your setup, Maya may or may not export namespaces within the names of objects in a
 /// model. This can result in mismatches if only certain meshes have namespaces. For example, exporting
@@ -13,23 +13,101 @@ lazy_static! {
 ///
 /// This check ensures that no namespaces are exported with a mesh. This is a sensible default as
 /// namespaces are unnecessary in Unity, and just add noise.
-pub fn verify(doc: &Document) -> anyhow::Result<Vec<String>> {
-    let mut errors = vec![];
-
-    for obj in doc.objects() {
-        if let TypedObjectHandle::Model(model) = obj.get_typed() {
-            let name = model.name();
-            match name {
-                None => {}
-                Some(name) => {
-                    if RE_NAMESPACE.is_match(name) {
-                        errors.push(format!("Objects should not be exported with namespaces: [{name}]"))
+///
+/// `--fix` removes everything up to the last `:` from model names, unless that would give two
+/// models the same name.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoNamespaces {
+    /// Model names matching this pattern are considered to have a namespace.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+}
+
+impl Default for NoNamespaces {
+    fn default() -> Self {
+        NoNamespaces {
+            pattern: Regex::new(r"^(.*):.*$").unwrap(),
+        }
+    }
+}
+
+impl Check for NoNamespaces {
+    fn id(&self) -> &'static str {
+        "no_namespaces"
+    }
+
+    fn title(&self) -> &'static str {
+        "Objects should not have namespaces"
+    }
+
+    fn description(&self) -> &'static str {
+        "Requires all model names to be exported without namespaces."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "Depending on your setup, Maya may or may not export namespaces within the names of \
+         objects in a model. This can result in mismatches if only certain meshes have \
+         namespaces. For example, exporting a rig with namespaces, but an animation pointing at \
+         that rig without namespaces.\n\n\
+         Namespaces are unnecessary in Unity, and just add noise. --fix removes everything up to \
+         the last ':' from model names, unless that would give two models the same name."
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+
+        for obj in doc.objects() {
+            if let TypedObjectHandle::Model(model) = obj.get_typed() {
+                let name = model.name();
+                match name {
+                    None => {}
+                    Some(name) => {
+                        if self.pattern.is_match(name) {
+                            errors.push(
+                                Diagnostic::new(format!(
+                                    "Objects should not be exported with namespaces: [{name}]"
+                                ))
+                                .with_object(&obj, name),
+                            )
+                        }
                     }
                 }
             }
-
         }
+
+        Ok(errors)
     }
 
-    Ok(errors)
+    fn fix(&self, _doc: &Document, tree: &mut Node) -> anyhow::Result<Vec<String>> {
+        let objects = match tree.child_mut("Objects") {
+            Some(objects) => objects,
+            None => return Ok(vec![]),
+        };
+
+        let mut names: Vec<String> = objects
+            .children_by_name("Model")
+            .filter_map(|m| m.object_name().map(str::to_owned))
+            .collect();
+
+        let mut changes = vec![];
+        for model in objects.children_by_name_mut("Model") {
+            let name = match model.object_name() {
+                Some(name) if self.pattern.is_match(name) => name.to_owned(),
+                _ => continue,
+            };
+
+            let stripped = name.rsplit(':').next().unwrap_or(&name).to_owned();
+            if stripped.is_empty() || self.pattern.is_match(&stripped) || names.contains(&stripped)
+            {
+                continue;
+            }
+
+            model.set_object_name(&stripped);
+            changes.push(format!("Renamed [{}] to [{}].", name, stripped));
+            names.push(stripped);
+        }
+
+        Ok(changes)
+    }
 }
@@ -1,6 +1,10 @@
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
+use crate::tree::{connections, Node};
 use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
 use fbxcel_dom::v7400::object::TypedObjectHandle;
 use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
 
 /// Maya uses 'Scale Compensation' when scaling joints in an animation rig.
 /// This means that when you scale a bone by 2x, children are *not* scaled. They are translated instead. This makes animating, more comfortable
@@ -13,27 +17,128 @@ use fbxcel_dom::v7400::Document;
 /// Instead child bones inherit the scale from their parents, causing wonky scaling issues.
 ///
 /// This check requires scale compensation to be disabled on all bones.
-pub fn verify(doc: &Document) -> anyhow::Result<Vec<String>> {
-    let mut errors = vec![];
-
-    for obj in doc.objects() {
-        if let TypedObjectHandle::Model(model) = obj.get_typed() {
-            if let Some(props) = model.direct_properties() {
-                if let Some(inherit_type_prop) = props.get_property("InheritType") {
-                    if let AttributeValue::I32(inherit_type) =
-                        inherit_type_prop.value_part().get(0).expect("no value found for attribute InheritType")
-                    {
-                        // InheritType 2 is used for scale compensation.
-                        // See: https://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_anim_curve_filter_scale_compensate_html
-                        // See: https://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_fbxtransforms_8h_source_html
-                        if *inherit_type == 2 {
-                            errors.push(format!("The bone [{}] has scale compensation enabled. Disable it in Maya before importing into Unity. (InheritType==eInheritRrs)", model.name().unwrap_or("(no name)")));
+///
+/// Scale compensation only makes a difference when the parent bone is scaled. `--fix` disables it
+/// (InheritType==eInheritRSrs) on bones whose parent has a constant scale of 1, which doesn't
+/// change the pose. Bones with scaled or scale-animated parents need to be fixed in Maya.
+#[derive(Default, Deserialize)]
+pub struct NoScaleCompensation;
+
+impl Check for NoScaleCompensation {
+    fn id(&self) -> &'static str {
+        "no_scale_compensation"
+    }
+
+    fn title(&self) -> &'static str {
+        "Scaling compensation is enabled"
+    }
+
+    fn description(&self) -> &'static str {
+        "Maya animations use Scale Compensation by default. Unity (or any other tool) doesn't support this attribute."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "Maya uses 'Scale Compensation' when scaling joints in an animation rig: when you scale a \
+         bone by 2x, children are not scaled, but translated instead. This is encoded in the FBX \
+         file as a counter-animation. All bones inherit scale from their parents, and the scale \
+         compensation parameter adjusts the final scale after the fact.\n\n\
+         Only Maya supports this property in its FBX importer. In Blender, Unity and 3DSMax, \
+         child bones inherit the scale from their parents instead, causing wonky scaling issues. \
+         --fix disables scale compensation on bones whose parent has a constant scale of 1, which \
+         doesn't change the pose. Bones with scaled or scale-animated parents need to be fixed in \
+         Maya."
+    }
+
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+
+        for obj in doc.objects() {
+            if let TypedObjectHandle::Model(model) = obj.get_typed() {
+                if let Some(props) = model.direct_properties() {
+                    if let Some(inherit_type_prop) = props.get_property("InheritType") {
+                        if let AttributeValue::I32(inherit_type) = inherit_type_prop
+                            .value_part()
+                            .get(0)
+                            .expect("no value found for attribute InheritType")
+                        {
+                            // InheritType 2 is used for scale compensation.
+                            // See: https://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_anim_curve_filter_scale_compensate_html
+                            // See: https://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_fbxtransforms_8h_source_html
+                            if *inherit_type == 2 {
+                                let name = model.name().unwrap_or("(no name)");
+                                errors.push(
+                                    Diagnostic::new(format!("The bone [{}] has scale compensation enabled. Disable it in Maya before importing into Unity. (InheritType==eInheritRrs)", name))
+                                        .with_object(&obj, name)
+                                        .measured(inherit_type)
+                                        .expected("0 or 1"),
+                                );
+                            }
                         }
                     }
                 }
             }
         }
+
+        Ok(errors)
     }
 
-    Ok(errors)
+    fn fix(&self, _doc: &Document, tree: &mut Node) -> anyhow::Result<Vec<String>> {
+        let connections = connections(tree);
+        let objects = match tree.child_mut("Objects") {
+            Some(objects) => objects,
+            None => return Ok(vec![]),
+        };
+
+        // Parents with a constant scale of 1. The scene root (id 0) is never scaled.
+        let unscaled: Vec<i64> = objects
+            .children_by_name("Model")
+            .filter_map(|model| {
+                let id = model.object_id()?;
+                let is_unit = match model.property_values("Lcl Scaling") {
+                    Some(
+                        [AttributeValue::F64(x), AttributeValue::F64(y), AttributeValue::F64(z)],
+                    ) => [x, y, z].iter().all(|s| (**s - 1.0).abs() < 1e-9),
+                    _ => true,
+                };
+                let is_animated = connections.iter().any(|c| {
+                    c.kind == "OP" && c.parent == id && c.property.as_deref() == Some("Lcl Scaling")
+                });
+                if is_unit && !is_animated {
+                    Some(id)
+                } else {
+                    None
+                }
+            })
+            .chain(std::iter::once(0))
+            .collect();
+
+        let mut changes = vec![];
+        for model in objects.children_by_name_mut("Model") {
+            let is_compensated = matches!(
+                model.property_values("InheritType"),
+                Some([AttributeValue::I32(2), ..])
+            );
+            if !is_compensated {
+                continue;
+            }
+
+            let id = model.object_id();
+            let parent = connections
+                .iter()
+                .find(|c| c.kind == "OO" && Some(c.child) == id)
+                .map(|c| c.parent)
+                .unwrap_or(0);
+            if !unscaled.contains(&parent) {
+                continue;
+            }
+
+            model.set_property("InheritType", "enum", "", vec![AttributeValue::I32(1)]);
+            changes.push(format!(
+                "Disabled scale compensation on the bone [{}].",
+                model.object_name().unwrap_or("(no name)")
+            ));
+        }
+
+        Ok(changes)
+    }
 }
@@ -1,81 +1,141 @@
+use crate::checks::Check;
+use crate::diagnostic::{Diagnostic, Severity};
 use crate::utils::get_model_roots;
 use cgmath::{AbsDiffEq, Zero};
 use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
 
 /// Verifies that files with a single root have identity rotation and scale. Having 90 degree rotations
 /// on all objects makes it very hard to use them in gameplay scripting.
 ///
 /// Files with multiple roots will be imported with an empty parent in Unity. In those cases
 /// non-identity transforms are ok.
-pub fn verify(doc: &Document) -> anyhow::Result<Vec<String>> {
-    // Some tools like Max will output the correct transforms, with slight error, so we
-    // check all of these using an epsilon.
-    // However, we try to have as tight a bound as possible, so each epsilon is separate.
-    const ROT_EPSILON: f64 = 0.00001f64; // 3ds max
-    const SCL_EPSILON: f64 = 0.000000000001f64; // 3ds max
-    #[allow(dead_code)]
-    const TRA_EPSILON: f64 = 0.000000000001f64; // 3ds max exports translates as small as this sometimes
+///
+/// Some tools like Max will output the correct transforms, with slight error, so we
+/// check all of these using an epsilon.
+/// However, we try to have as tight a bound as possible, so each epsilon is separate.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RootHasIdentityTransform {
+    pub rotation_epsilon: f64,
+    pub scale_epsilon: f64,
+    pub translation_epsilon: f64,
+}
+
+impl Default for RootHasIdentityTransform {
+    fn default() -> Self {
+        RootHasIdentityTransform {
+            rotation_epsilon: 0.00001f64,           // 3ds max
+            scale_epsilon: 0.000000000001f64,       // 3ds max
+            translation_epsilon: 0.000000000001f64, // 3ds max exports translates as small as this sometimes
+        }
+    }
+}
 
-    let mut errors = vec![];
+impl Check for RootHasIdentityTransform {
+    fn id(&self) -> &'static str {
+        "root_has_identity_transform"
+    }
 
-    let roots = get_model_roots(doc);
+    fn title(&self) -> &'static str {
+        "Root does not have zero transform"
+    }
 
-    // Only files with a single root model are considered for this check.
-    if roots.len() != 1 {
-        return Ok(errors);
+    fn description(&self) -> &'static str {
+        "Verifies a single root object has an identity transform."
     }
-    for root in get_model_roots(doc) {
-        let name = root.name().unwrap_or("(object has no name)");
 
-        // Note(john): Disabling translation check for now. It's not proved to be a big problem in Unity,
-        // except having it spawn in a weird place when dragging it into the hierarchy.
-        // No translation implies a zero translation.
-        // if let Some(translate) = root.local_translation()? {
-        //     let t: cgmath::Vector3<f64> = translate.into();
-        //     if t.abs_diff_ne(&cgmath::Vector3::<f64>::zero(), TRA_EPSILON) {
-        //         errors.push(format!(
-        //             "The root object [{}] does not have a zero translation. It has translate: [{:?}]",
-        //             name, t
-        //         ));
-        //     }
-        // }
+    fn rationale(&self) -> &'static str {
+        "Having 90 degree rotations on all objects makes them very hard to use in gameplay \
+         scripting, so files with a single root must have an identity rotation and scale on it. \
+         Files with multiple roots are imported with an empty parent in Unity, so non-identity \
+         transforms are ok there.\n\n\
+         Some tools like Max output the correct transforms with slight error, so each part of the \
+         transform is compared with its own, tight epsilon."
+    }
 
-        // No rotation implies a zero rotation.
-        if let Some(rot) = root.local_rotation()? {
-            let r: cgmath::Vector3<f64> = rot.into();
-            if r.abs_diff_ne(&cgmath::Vector3::<f64>::zero(), ROT_EPSILON) {
-                errors.push(format!(
-                    "The root object [{}] does not have a zero rotation. It has rotation: [{:?}]",
-                    name, r
-                ));
-            }
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let mut errors = vec![];
+
+        let roots = get_model_roots(doc);
+
+        // Only files with a single root model are considered for this check.
+        if roots.len() != 1 {
+            return Ok(errors);
         }
+        for root in get_model_roots(doc) {
+            let name = root.name().unwrap_or("(object has no name)");
 
-        // Pre-Rotation is set from 3DS Max on export, usually.
-        // No rotation implies a zero rotation.
-        // https://download.autodesk.com/us/fbx/20112/FBX_SDK_HELP/index.html?url=WS1a9193826455f5ff1f92379812724681e696651.htm,topicNumber=d0e7429
-        if let Some(rot) = root.pre_rotation()? {
-            let r: cgmath::Vector3<f64> = rot.into();
-            if r.abs_diff_ne(&cgmath::Vector3::<f64>::zero(), ROT_EPSILON) {
-                errors.push(format!(
-                    "The root object [{}] does not have a zero rotation. It has pre-rotation: [{:?}]",
-                    name, r
-                ));
+            // A translated root is not a big problem in Unity, except having it spawn in a weird
+            // place when dragging it into the hierarchy. So this is only reported as a warning.
+            // No translation implies a zero translation.
+            if let Some(translate) = root.local_translation()? {
+                let t: cgmath::Vector3<f64> = translate.into();
+                if t.abs_diff_ne(&cgmath::Vector3::<f64>::zero(), self.translation_epsilon) {
+                    errors.push(
+                        Diagnostic::new(format!(
+                            "The root object [{}] does not have a zero translation. It has translate: [{:?}]",
+                            name, t
+                        ))
+                        .with_object(&root, name)
+                        .with_severity(Severity::Warning)
+                        .measured(format!("({}, {}, {})", t.x, t.y, t.z))
+                        .expected("(0, 0, 0)"),
+                    );
+                }
             }
-        }
 
-        // No scale implies a scale of 1
-        if let Some(scl) = root.local_scaling()? {
-            let s: cgmath::Vector3<f64> = scl.into();
+            // No rotation implies a zero rotation.
+            if let Some(rot) = root.local_rotation()? {
+                let r: cgmath::Vector3<f64> = rot.into();
+                if r.abs_diff_ne(&cgmath::Vector3::<f64>::zero(), self.rotation_epsilon) {
+                    errors.push(Diagnostic::new(format!(
+                        "The root object [{}] does not have a zero rotation. It has rotation: [{:?}]",
+                        name, r
+                    ))
+                    .with_object(&root, name)
+                    .measured(format!("({}, {}, {})", r.x, r.y, r.z))
+                    .expected("(0, 0, 0)"));
+                }
+            }
 
-            if s.abs_diff_ne(&cgmath::Vector3::<f64>::new(1f64, 1f64, 1f64), SCL_EPSILON) {
-                errors.push(format!(
-                    "The root object [{}] does not have a scale of 1. It has scale: [{:?}]",
-                    name, s
-                ));
+            // Pre-Rotation is set from 3DS Max on export, usually.
+            // No rotation implies a zero rotation.
+            // https://download.autodesk.com/us/fbx/20112/FBX_SDK_HELP/index.html?url=WS1a9193826455f5ff1f92379812724681e696651.htm,topicNumber=d0e7429
+            if let Some(rot) = root.pre_rotation()? {
+                let r: cgmath::Vector3<f64> = rot.into();
+                if r.abs_diff_ne(&cgmath::Vector3::<f64>::zero(), self.rotation_epsilon) {
+                    errors.push(Diagnostic::new(format!(
+                        "The root object [{}] does not have a zero rotation. It has pre-rotation: [{:?}]",
+                        name, r
+                    ))
+                    .with_object(&root, name)
+                    .measured(format!("({}, {}, {})", r.x, r.y, r.z))
+                    .expected("(0, 0, 0)"));
+                }
+            }
+
+            // No scale implies a scale of 1
+            if let Some(scl) = root.local_scaling()? {
+                let s: cgmath::Vector3<f64> = scl.into();
+
+                if s.abs_diff_ne(
+                    &cgmath::Vector3::<f64>::new(1f64, 1f64, 1f64),
+                    self.scale_epsilon,
+                ) {
+                    errors.push(
+                        Diagnostic::new(format!(
+                            "The root object [{}] does not have a scale of 1. It has scale: [{:?}]",
+                            name, s
+                        ))
+                        .with_object(&root, name)
+                        .measured(format!("({}, {}, {})", s.x, s.y, s.z))
+                        .expected("(1, 1, 1)"),
+                    );
+                }
             }
         }
-    }
 
-    Ok(errors)
+        Ok(errors)
+    }
 }
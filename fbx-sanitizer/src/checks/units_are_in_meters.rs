@@ -1,27 +1,122 @@
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
+use crate::fix::unit_scale;
+use crate::tree::Node;
 use crate::utils::{get_application_name, ApplicationName};
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
 use fbxcel_dom::v7400::Document;
+use serde::Deserialize;
 
 /// If units are not in meters, Unity will apply a scale when loading.
 /// If units are not set at all, generates an error because some software will assume CM (Blender) while others will not (Unity).
-#[allow(clippy::float_cmp)]
-pub fn verify(doc: &Document) -> Vec<String> {
-    let correct_unit: f64 = match get_application_name(doc) {
-        Some(ApplicationName::Maya) => 1f64, // Maya cannot export in meters properly. This is the cm 'hack'
-        Some(ApplicationName::Houdini) => 1f64, // Houdini cannot export in anything but centimeters. We use the same 'cm' hack.
-        Some(_) | None => 100f64,               // all other applications should export meters
-    };
-
-    let file_unit = match doc.global_settings() {
-        Some(settings) => settings.unit_scale_factor(),
-        None => 1.0,
-    };
-
-    if file_unit != correct_unit {
-        return vec![format!(
-            "File is not in the correct units. Units: {}cm. Should be {}cm.",
-            file_unit, correct_unit
-        )];
-    }
-
-    vec![]
+///
+/// The expected unit is configured per application, as the `UnitScaleFactor` (size of one unit in cm).
+///
+/// `--fix` converts the file to the expected unit, and rescales the scene so it keeps the same
+/// size in Unity. See `fix::unit_scale`.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnitsAreInMeters {
+    pub blender: f64,
+    pub max: f64,
+    pub maya: f64,
+    pub houdini: f64,
+    /// Any other application, or files that don't say which application saved them.
+    pub other: f64,
+}
+
+impl Default for UnitsAreInMeters {
+    fn default() -> Self {
+        UnitsAreInMeters {
+            blender: 100f64,
+            max: 100f64,
+            maya: 1f64,    // Maya cannot export in meters properly. This is the cm 'hack'
+            houdini: 1f64, // Houdini cannot export in anything but centimeters. We use the same 'cm' hack.
+            other: 100f64, // all other applications should export meters
+        }
+    }
+}
+
+impl UnitsAreInMeters {
+    /// The unit scale factor a file saved by the given application should have.
+    pub fn expected_unit(&self, application_name: &Option<ApplicationName>) -> f64 {
+        match application_name {
+            Some(ApplicationName::Blender) => self.blender,
+            Some(ApplicationName::Max) => self.max,
+            Some(ApplicationName::Maya) => self.maya,
+            Some(ApplicationName::Houdini) => self.houdini,
+            Some(ApplicationName::UnknownApplication(_)) | None => self.other,
+        }
+    }
+}
+
+impl Check for UnitsAreInMeters {
+    fn id(&self) -> &'static str {
+        "units_are_in_meters"
+    }
+
+    fn title(&self) -> &'static str {
+        "Units not in meters"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verifies the file is in Meters units. Maya and Houdini exports are expected in centimeters instead."
+    }
+
+    fn rationale(&self) -> &'static str {
+        "If units are not in meters, Unity will apply a scale when loading. If units are not set \
+         at all, some software will assume centimeters (Blender) while others will not (Unity).\n\n\
+         The expected unit is configured per application. --fix converts the file to the expected \
+         unit, and rescales the scene so it keeps the same size in Unity."
+    }
+
+    #[allow(clippy::float_cmp)]
+    fn run(&self, doc: &Document) -> anyhow::Result<Vec<Diagnostic>> {
+        let correct_unit = self.expected_unit(&get_application_name(doc));
+
+        let file_unit = match doc.global_settings() {
+            Some(settings) => settings.unit_scale_factor(),
+            None => 1.0,
+        };
+
+        if file_unit != correct_unit {
+            return Ok(vec![Diagnostic::new(format!(
+                "File is not in the correct units. Units: {}cm. Should be {}cm.",
+                file_unit, correct_unit
+            ))
+            .measured(file_unit)
+            .expected(correct_unit)]);
+        }
+
+        Ok(vec![])
+    }
+
+    fn fix(&self, doc: &Document, tree: &mut Node) -> anyhow::Result<Vec<String>> {
+        let correct_unit = self.expected_unit(&get_application_name(doc));
+        let file_unit = match doc.global_settings() {
+            Some(settings) => settings.unit_scale_factor(),
+            None => 1.0,
+        };
+
+        if tree.child("GlobalSettings").is_none() {
+            return Ok(vec![]);
+        }
+
+        // A unit of the old size is `factor` units of the new size.
+        let factor = file_unit / correct_unit;
+        unit_scale::rescale(tree, factor);
+        if let Some(settings) = tree.child_mut("GlobalSettings") {
+            settings.set_property(
+                "UnitScaleFactor",
+                "double",
+                "Number",
+                vec![AttributeValue::F64(correct_unit)],
+            );
+        }
+
+        Ok(vec![format!(
+            "Converted the file from {}cm to {}cm units, scaling the scene by {}.",
+            file_unit, correct_unit, factor
+        )])
+    }
 }
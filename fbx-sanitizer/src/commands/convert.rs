@@ -0,0 +1,21 @@
+use crate::ascii::read_as_binary;
+use crate::utils::parse_document;
+use anyhow::Context;
+use std::path::Path;
+
+/// Converts an ASCII fbx file to binary fbx. The converted file is parsed before it is written,
+/// so a file that can't be read back is never written. Binary files are copied as-is.
+pub fn run(args: &clap::ArgMatches) -> anyhow::Result<()> {
+    let input = Path::new(args.value_of("input").expect("input is required"));
+    let output = Path::new(args.value_of("output").expect("output is required"));
+
+    let (bytes, _) = read_as_binary(input)
+        .with_context(|| format!("Could not read fbx file {}", input.display()))?;
+    parse_document(&bytes)
+        .with_context(|| format!("Could not parse the converted file {}", input.display()))?;
+    std::fs::write(output, bytes)
+        .with_context(|| format!("Could not write {}", output.display()))?;
+
+    log::info!("Converted {} to {}", input.display(), output.display());
+    Ok(())
+}
@@ -0,0 +1,416 @@
+use crate::ascii::read_as_binary;
+use crate::tree::Node;
+use crate::utils::{geo_name, get_models, model_type, parse_document, stack_seconds};
+use anyhow::Context;
+use cgmath::{Vector3, Zero};
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use fbxcel_dom::v7400::object::geometry::TypedGeometryHandle;
+use fbxcel_dom::v7400::object::TypedObjectHandle;
+use fbxcel_dom::v7400::Document;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The formats `diff` can print.
+pub const FORMATS: &[&str] = &["text", "json"];
+
+/// The items of one part of a file (models, meshes, ...), by name. Each item has a set of named
+/// fields, formatted as text so they can be compared and printed the same way.
+type Section = BTreeMap<String, BTreeMap<&'static str, String>>;
+
+/// Everything that is compared between two files, as sections in the order they are printed.
+type Snapshot = Vec<(&'static str, Section)>;
+
+#[derive(Serialize)]
+struct Diff<'a> {
+    a: String,
+    b: String,
+    changes: Vec<Change<'a>>,
+}
+
+/// An item that is only in one of the files, or whose fields are different.
+#[derive(Serialize)]
+struct Change<'a> {
+    section: &'static str,
+    item: &'a str,
+    change: ChangeKind,
+    /// The fields that are different. Added and removed items list all of their fields.
+    fields: Vec<FieldChange<'a>>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize)]
+struct FieldChange<'a> {
+    field: &'static str,
+    a: Option<&'a str>,
+    b: Option<&'a str>,
+}
+
+/// Compares two files, and prints what changed between them: global settings, the model
+/// hierarchy and transforms, mesh sizes and bounds, materials, skins and animation stacks.
+///
+/// Objects are matched by name (models by their path in the hierarchy), since ids change every
+/// time a file is exported.
+pub fn run(args: &clap::ArgMatches) -> anyhow::Result<()> {
+    let a = Path::new(args.value_of("a").unwrap());
+    let b = Path::new(args.value_of("b").unwrap());
+    let before =
+        read_snapshot(a).with_context(|| format!("Could not read fbx file {}", a.display()))?;
+    let after =
+        read_snapshot(b).with_context(|| format!("Could not read fbx file {}", b.display()))?;
+
+    let diff = Diff {
+        a: a.display().to_string(),
+        b: b.display().to_string(),
+        changes: compare(&before, &after),
+    };
+    match args.value_of("format") {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&diff)?),
+        _ => print_text(&diff),
+    }
+    Ok(())
+}
+
+fn read_snapshot(path: &Path) -> anyhow::Result<Snapshot> {
+    let (bytes, _) = read_as_binary(path)?;
+    let (_, doc) = parse_document(&bytes)?;
+    let root = Node::from_handle(&doc.tree().root());
+
+    Ok(vec![
+        ("settings", settings(&root)),
+        ("models", models(&doc)?),
+        ("meshes", meshes(&doc)?),
+        ("materials", materials(&doc)),
+        ("skins", skins(&doc)),
+        ("animations", animations(&root)),
+    ])
+}
+
+fn compare<'a>(before: &'a Snapshot, after: &'a Snapshot) -> Vec<Change<'a>> {
+    let mut changes = vec![];
+    for ((section, a), (_, b)) in before.iter().zip(after) {
+        let mut items: Vec<&String> = a.keys().chain(b.keys()).collect();
+        items.sort();
+        items.dedup();
+
+        for item in items {
+            let (a, b) = (a.get(item), b.get(item));
+            let change = match (a, b) {
+                (Some(_), None) => ChangeKind::Removed,
+                (None, Some(_)) => ChangeKind::Added,
+                _ => ChangeKind::Changed,
+            };
+
+            let mut fields: Vec<&'static str> = a
+                .into_iter()
+                .chain(b)
+                .flat_map(|f| f.keys().copied())
+                .collect();
+            fields.sort_unstable();
+            fields.dedup();
+            let fields: Vec<FieldChange> = fields
+                .into_iter()
+                .map(|field| FieldChange {
+                    field,
+                    a: a.and_then(|f| f.get(field)).map(String::as_str),
+                    b: b.and_then(|f| f.get(field)).map(String::as_str),
+                })
+                .filter(|f| f.a != f.b)
+                .collect();
+
+            if change != ChangeKind::Changed || !fields.is_empty() {
+                changes.push(Change {
+                    section: *section,
+                    item,
+                    change,
+                    fields,
+                });
+            }
+        }
+    }
+    changes
+}
+
+fn print_text(diff: &Diff) {
+    println!("--- {}", diff.a);
+    println!("+++ {}", diff.b);
+    if diff.changes.is_empty() {
+        println!("No differences.");
+        return;
+    }
+
+    let mut section = "";
+    for change in &diff.changes {
+        if change.section != section {
+            section = change.section;
+            println!("{}:", section);
+        }
+        // Settings have a single value, which fits on the same line.
+        if let [FieldChange {
+            field: "value",
+            a,
+            b,
+        }] = change.fields.as_slice()
+        {
+            match change.change {
+                ChangeKind::Added => println!("  + {}: {}", change.item, b.unwrap_or("")),
+                ChangeKind::Removed => println!("  - {}: {}", change.item, a.unwrap_or("")),
+                ChangeKind::Changed => println!(
+                    "  ~ {}: {} -> {}",
+                    change.item,
+                    a.unwrap_or(""),
+                    b.unwrap_or("")
+                ),
+            }
+            continue;
+        }
+
+        match change.change {
+            ChangeKind::Added => {
+                println!("  + {}", change.item);
+                for field in &change.fields {
+                    println!("      {}: {}", field.field, field.b.unwrap_or(""));
+                }
+            }
+            ChangeKind::Removed => println!("  - {}", change.item),
+            ChangeKind::Changed => {
+                println!("  ~ {}", change.item);
+                for field in &change.fields {
+                    println!(
+                        "      {}: {} -> {}",
+                        field.field,
+                        field.a.unwrap_or("(none)"),
+                        field.b.unwrap_or("(none)")
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Every property of `GlobalSettings`, ie. the units and axes.
+fn settings(root: &Node) -> Section {
+    root.child("GlobalSettings")
+        .and_then(|s| s.child("Properties70"))
+        .into_iter()
+        .flat_map(|p| p.children_by_name("P"))
+        .filter_map(|p| {
+            let name = p.attributes.get(0).and_then(AttributeValue::get_string)?;
+            let value: Vec<String> = p.attributes.iter().skip(4).map(format_value).collect();
+            let mut fields = BTreeMap::new();
+            fields.insert("value", value.join(", "));
+            Some((name.to_owned(), fields))
+        })
+        .collect()
+}
+
+/// Models, by their path in the hierarchy, ie. "Root/Arm/Hand".
+fn models(doc: &Document) -> anyhow::Result<Section> {
+    let count = get_models(doc).count();
+    let mut section = Section::new();
+    for model in get_models(doc) {
+        let mut path = vec![model.name().unwrap_or("(no name)")];
+        let mut parent = model.parent_model();
+        while let Some(p) = parent {
+            // Bounded, in case of a (broken) cycle.
+            if path.len() > count {
+                break;
+            }
+            path.push(p.name().unwrap_or("(no name)"));
+            parent = p.parent_model();
+        }
+        path.reverse();
+
+        // Transforms that aren't set are the identity.
+        let t: Vector3<f64> = model
+            .local_translation()?
+            .map(Into::into)
+            .unwrap_or_else(Vector3::zero);
+        let r: Vector3<f64> = model
+            .local_rotation()?
+            .map(Into::into)
+            .unwrap_or_else(Vector3::zero);
+        let s: Vector3<f64> = model
+            .local_scaling()?
+            .map(Into::into)
+            .unwrap_or_else(|| Vector3::new(1.0, 1.0, 1.0));
+        let pre: Vector3<f64> = model
+            .pre_rotation()?
+            .map(Into::into)
+            .unwrap_or_else(Vector3::zero);
+
+        let mut fields = BTreeMap::new();
+        fields.insert("type", model_type(model.subclass()));
+        fields.insert("translation", format_vector(t));
+        fields.insert("rotation", format_vector(r));
+        fields.insert("scale", format_vector(s));
+        fields.insert("pre-rotation", format_vector(pre));
+        insert_unique(&mut section, path.join("/"), fields);
+    }
+    Ok(section)
+}
+
+/// Meshes, by the name of their model. See `utils::geo_name`.
+fn meshes(doc: &Document) -> anyhow::Result<Section> {
+    let mut section = Section::new();
+    for obj in doc.objects() {
+        if let TypedObjectHandle::Geometry(geo) = obj.get_typed() {
+            if let TypedGeometryHandle::Mesh(m) = geo {
+                let polygon_vertices = m.polygon_vertices()?;
+                let indices = polygon_vertices.raw_polygon_vertices();
+                // Each polygon ends with a negative index, and has (size - 2) triangles.
+                let polygons = indices.iter().filter(|&&i| i < 0).count();
+                let triangles = indices.len().saturating_sub(2 * polygons);
+
+                let mut vertices = 0;
+                let mut min = Vector3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+                let mut max = Vector3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+                for p in polygon_vertices.raw_control_points()? {
+                    vertices += 1;
+                    min = Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+                    max = Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+                }
+
+                let mut fields = BTreeMap::new();
+                fields.insert("vertices", vertices.to_string());
+                fields.insert("polygons", polygons.to_string());
+                fields.insert("triangles", triangles.to_string());
+                fields.insert(
+                    "bounds",
+                    if vertices == 0 {
+                        "(empty)".to_owned()
+                    } else {
+                        format!("{} to {}", format_vector(min), format_vector(max))
+                    },
+                );
+                insert_unique(&mut section, geo_name(&geo)?.to_owned(), fields);
+            }
+        }
+    }
+    Ok(section)
+}
+
+/// Materials, with the models that use them.
+fn materials(doc: &Document) -> Section {
+    let mut section = Section::new();
+    for obj in doc.objects().filter(|o| o.class() == "Material") {
+        let mut models: Vec<&str> = obj
+            .destination_objects()
+            .filter_map(|o| o.object_handle())
+            .filter(|o| o.class() == "Model")
+            .map(|o| o.name().unwrap_or("(no name)"))
+            .collect();
+        models.sort_unstable();
+
+        let mut fields = BTreeMap::new();
+        fields.insert("models", models.join(", "));
+        insert_unique(
+            &mut section,
+            obj.name().unwrap_or("(no name)").to_owned(),
+            fields,
+        );
+    }
+    section
+}
+
+/// Skins, by the name of the mesh they deform, with the set of bones they are bound to.
+fn skins(doc: &Document) -> Section {
+    let mut section = Section::new();
+    for skin in doc.objects().filter(|o| o.subclass() == "Skin") {
+        let mesh = skin
+            .destination_objects()
+            .filter_map(|o| o.object_handle())
+            .find_map(|o| match o.get_typed() {
+                TypedObjectHandle::Geometry(geo) => geo_name(&geo).ok(),
+                _ => None,
+            })
+            .or_else(|| skin.name())
+            .unwrap_or("(no name)");
+
+        // A skin has a cluster for each bone, and each cluster is connected to its bone.
+        let clusters: Vec<_> = skin
+            .source_objects()
+            .filter_map(|o| o.object_handle())
+            .filter(|o| o.subclass() == "Cluster")
+            .collect();
+        let mut bones: Vec<&str> = clusters
+            .iter()
+            .flat_map(|c| c.source_objects())
+            .filter_map(|o| o.object_handle())
+            .filter(|o| o.class() == "Model")
+            .map(|o| o.name().unwrap_or("(no name)"))
+            .collect();
+        bones.sort_unstable();
+
+        let mut fields = BTreeMap::new();
+        fields.insert("clusters", clusters.len().to_string());
+        fields.insert("bones", bones.join(", "));
+        insert_unique(&mut section, mesh.to_owned(), fields);
+    }
+    section
+}
+
+/// Animation stacks, with their length.
+fn animations(root: &Node) -> Section {
+    let mut section = Section::new();
+    for stack in root
+        .child("Objects")
+        .into_iter()
+        .flat_map(|o| o.children_by_name("AnimationStack"))
+    {
+        let mut fields = BTreeMap::new();
+        fields.insert(
+            "length",
+            match stack_seconds(stack) {
+                Some(seconds) => format!("{:.2}s", seconds),
+                None => "(unknown)".to_owned(),
+            },
+        );
+        insert_unique(
+            &mut section,
+            stack.object_name().unwrap_or("(no name)").to_owned(),
+            fields,
+        );
+    }
+    section
+}
+
+/// Inserts an item, numbering it if another item already has its name, so neither is lost.
+fn insert_unique(section: &mut Section, name: String, fields: BTreeMap<&'static str, String>) {
+    let mut key = name.clone();
+    let mut n = 2;
+    while section.contains_key(&key) {
+        key = format!("{} ({})", name, n);
+        n += 1;
+    }
+    section.insert(key, fields);
+}
+
+fn format_vector(v: Vector3<f64>) -> String {
+    format!("({}, {}, {})", v.x, v.y, v.z)
+}
+
+fn format_value(value: &AttributeValue) -> String {
+    match value {
+        AttributeValue::Bool(v) => v.to_string(),
+        AttributeValue::I16(v) => v.to_string(),
+        AttributeValue::I32(v) => v.to_string(),
+        AttributeValue::I64(v) => v.to_string(),
+        AttributeValue::F32(v) => v.to_string(),
+        AttributeValue::F64(v) => v.to_string(),
+        AttributeValue::String(v) => format!("\"{}\"", v),
+        AttributeValue::ArrBool(v) => format!("[{} values]", v.len()),
+        AttributeValue::ArrI32(v) => format!("[{} values]", v.len()),
+        AttributeValue::ArrI64(v) => format!("[{} values]", v.len()),
+        AttributeValue::ArrF32(v) => format!("[{} values]", v.len()),
+        AttributeValue::ArrF64(v) => format!("[{} values]", v.len()),
+        AttributeValue::Binary(v) => format!("[{} bytes]", v.len()),
+    }
+}
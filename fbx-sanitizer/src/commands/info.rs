@@ -0,0 +1,294 @@
+use crate::ascii::read_as_binary;
+use crate::checks::correct_coordinate_axis::get_coordinate_axis;
+use crate::tree::{connections, Connection, Node};
+use crate::utils::{
+    get_creator, get_model_roots, get_scene_info_string, is_bone, parse_document, stack_seconds,
+};
+use anyhow::Context;
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Prints a summary of what each file contains: where it came from, its scene settings, and how
+/// many of each kind of object it has.
+pub fn run(args: &clap::ArgMatches) -> anyhow::Result<()> {
+    let files: Vec<&Path> = args.values_of("files").unwrap().map(Path::new).collect();
+    for (i, path) in files.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_info(path).with_context(|| format!("Could not read fbx file {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn print_info(path: &Path) -> anyhow::Result<()> {
+    let (bytes, binary) = read_as_binary(path)?;
+    let (version, doc) = parse_document(&bytes)?;
+    let root = Node::from_handle(&doc.tree().root());
+    let settings = root.child("GlobalSettings");
+
+    println!("{}", path.display());
+    let field = |name: &str, value: &dyn std::fmt::Display| println!("  {:<14}{}", name, value);
+
+    field(
+        "FBX version:",
+        &format!(
+            "{}.{} ({})",
+            version.raw() / 1000,
+            version.raw() % 1000 / 100,
+            if binary { "binary" } else { "ASCII" }
+        ),
+    );
+    field("Creator:", &get_creator(&doc).unwrap_or("(unknown)"));
+    let application = get_scene_info_string(&doc, "LastSaved|ApplicationName");
+    let application_version = get_scene_info_string(&doc, "LastSaved|ApplicationVersion");
+    field(
+        "Application:",
+        &match (application, application_version) {
+            (Some(name), Some(version)) => format!("{} {}", name, version),
+            (Some(name), None) => name.to_owned(),
+            _ => "(unknown)".to_owned(),
+        },
+    );
+    field(
+        "Units:",
+        &match settings.and_then(|s| number_property(s, "UnitScaleFactor")) {
+            Some(unit) => format!("{}cm", unit),
+            None => "(unknown)".to_owned(),
+        },
+    );
+    field(
+        "Axis:",
+        &get_coordinate_axis(&doc)
+            .map(|axis| axis.display_triplet())
+            .unwrap_or_else(|| "(unknown)".to_owned()),
+    );
+    let frames_per_second = settings.and_then(frame_rate);
+    field(
+        "Frame rate:",
+        &match frames_per_second {
+            Some(fps) => format!("{} fps", fps),
+            None => "(unknown)".to_owned(),
+        },
+    );
+
+    let empty = Node::new("Objects", vec![]);
+    let objects = root.child("Objects").unwrap_or(&empty);
+    let connections = connections(&root);
+
+    // Models, by their type.
+    let models: Vec<&Node> = objects.children_by_name("Model").collect();
+    let mut model_types: Vec<(&str, usize)> = vec![];
+    for model in &models {
+        let model_type = match model.object_subclass().unwrap_or("") {
+            "Mesh" => "meshes",
+            "Null" => "nulls",
+            subclass if is_bone(subclass) => "bones",
+            "Camera" => "cameras",
+            "Light" => "lights",
+            _ => "other",
+        };
+        match model_types.iter_mut().find(|(t, _)| *t == model_type) {
+            Some((_, count)) => *count += 1,
+            None => model_types.push((model_type, 1)),
+        }
+    }
+    let model_types: Vec<String> = model_types
+        .iter()
+        .map(|(t, count)| format!("{} {}", count, t))
+        .collect();
+    field(
+        "Models:",
+        &format!(
+            "{} ({} roots, {} levels deep){}",
+            models.len(),
+            get_model_roots(&doc).len(),
+            hierarchy_depth(&models, &connections),
+            if model_types.is_empty() {
+                String::new()
+            } else {
+                format!(": {}", model_types.join(", "))
+            }
+        ),
+    );
+
+    // Meshes, and their size.
+    let meshes: Vec<&Node> = objects
+        .children_by_name("Geometry")
+        .filter(|g| g.object_subclass() == Some("Mesh"))
+        .collect();
+    let mut vertices = 0;
+    let mut polygons = 0;
+    let mut triangles = 0;
+    for mesh in &meshes {
+        if let Some(AttributeValue::ArrF64(positions)) =
+            mesh.child("Vertices").and_then(|v| v.attributes.get(0))
+        {
+            vertices += positions.len() / 3;
+        }
+        if let Some(AttributeValue::ArrI32(indices)) = mesh
+            .child("PolygonVertexIndex")
+            .and_then(|v| v.attributes.get(0))
+        {
+            // Each polygon ends with a negative index, and has (size - 2) triangles.
+            let ends = indices.iter().filter(|&&i| i < 0).count();
+            polygons += ends;
+            triangles += indices.len().saturating_sub(2 * ends);
+        }
+    }
+    field(
+        "Meshes:",
+        &format!(
+            "{} ({} vertices, {} polygons, {} triangles)",
+            meshes.len(),
+            vertices,
+            polygons,
+            triangles
+        ),
+    );
+
+    field(
+        "Materials:",
+        &list(objects.children_by_name("Material").map(object_name)),
+    );
+    field(
+        "Textures:",
+        &list(objects.children_by_name("Texture").map(|texture| {
+            texture
+                .child("RelativeFilename")
+                .or_else(|| texture.child("FileName"))
+                .and_then(|f| f.attributes.get(0))
+                .and_then(AttributeValue::get_string)
+                .filter(|f| !f.is_empty())
+                .map(str::to_owned)
+                .unwrap_or_else(|| object_name(texture))
+        })),
+    );
+
+    // Deformers.
+    let deformers = move |subclass: &'static str| {
+        objects
+            .children_by_name("Deformer")
+            .filter(move |d| d.object_subclass() == Some(subclass))
+    };
+    field(
+        "Skins:",
+        &format!(
+            "{} ({} clusters)",
+            deformers("Skin").count(),
+            deformers("Cluster").count()
+        ),
+    );
+    field(
+        "Bones:",
+        &models
+            .iter()
+            .filter(|m| is_bone(m.object_subclass().unwrap_or("")))
+            .count(),
+    );
+    field(
+        "Blend shapes:",
+        &format!(
+            "{} ({} channels)",
+            deformers("BlendShape").count(),
+            deformers("BlendShapeChannel").count()
+        ),
+    );
+
+    // Animation stacks, with their length.
+    let stacks: Vec<String> = objects
+        .children_by_name("AnimationStack")
+        .map(|stack| match (stack_seconds(stack), frames_per_second) {
+            (Some(seconds), Some(fps)) => format!(
+                "{} ({:.2}s, {} frames)",
+                object_name(stack),
+                seconds,
+                (seconds * fps).round()
+            ),
+            (Some(seconds), None) => format!("{} ({:.2}s)", object_name(stack), seconds),
+            (None, _) => object_name(stack),
+        })
+        .collect();
+    field("Animations:", &list(stacks.into_iter()));
+
+    Ok(())
+}
+
+fn object_name(object: &Node) -> String {
+    object.object_name().unwrap_or("(no name)").to_owned()
+}
+
+/// Formats a list of names as its length, followed by the names.
+fn list(names: impl Iterator<Item = String>) -> String {
+    let names: Vec<String> = names.collect();
+    if names.is_empty() {
+        "0".to_owned()
+    } else {
+        format!("{}: {}", names.len(), names.join(", "))
+    }
+}
+
+fn number_property(node: &Node, name: &str) -> Option<f64> {
+    match node.property_values(name)?.get(0)? {
+        AttributeValue::F64(v) => Some(*v),
+        AttributeValue::F32(v) => Some(f64::from(*v)),
+        AttributeValue::I32(v) => Some(f64::from(*v)),
+        _ => None,
+    }
+}
+
+/// The frame rate of the scene, from the `TimeMode` in `GlobalSettings` (FbxTime::EMode).
+fn frame_rate(settings: &Node) -> Option<f64> {
+    let mode = match settings.property_values("TimeMode")?.get(0)? {
+        AttributeValue::I32(mode) => *mode,
+        _ => return None,
+    };
+    Some(match mode {
+        1 => 120.0,
+        2 => 100.0,
+        3 => 60.0,
+        4 => 50.0,
+        5 => 48.0,
+        6 | 7 => 30.0,
+        8 | 9 => 29.97,
+        10 => 25.0,
+        11 => 24.0,
+        12 => 1000.0,
+        13 => 23.976,
+        14 => number_property(settings, "CustomFrameRate").filter(|&fps| fps > 0.0)?,
+        15 => 96.0,
+        16 => 72.0,
+        17 => 59.94,
+        18 => 119.88,
+        // The default mode, which depends on the application.
+        _ => return None,
+    })
+}
+
+/// The number of levels in the model hierarchy: 1 if every model is a root.
+fn hierarchy_depth(models: &[&Node], connections: &[Connection]) -> usize {
+    let ids: HashSet<i64> = models.iter().filter_map(|m| m.object_id()).collect();
+    let parents: HashMap<i64, i64> = connections
+        .iter()
+        .filter(|c| c.kind == "OO" && ids.contains(&c.child) && ids.contains(&c.parent))
+        .map(|c| (c.child, c.parent))
+        .collect();
+
+    ids.iter()
+        .map(|&id| {
+            let mut depth = 1;
+            let mut current = id;
+            // Bounded, in case of a (broken) cycle.
+            while let Some(&parent) = parents.get(&current) {
+                depth += 1;
+                current = parent;
+                if depth > ids.len() {
+                    break;
+                }
+            }
+            depth
+        })
+        .max()
+        .unwrap_or(0)
+}
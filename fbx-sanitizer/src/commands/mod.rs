@@ -0,0 +1,4 @@
+pub mod convert;
+pub mod diff;
+pub mod info;
+pub mod tree;
@@ -0,0 +1,244 @@
+use crate::ascii::read_as_binary;
+use crate::tree::Node;
+use crate::utils::{get_model_roots, get_models, model_type, parse_document};
+use anyhow::Context;
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Prints the model hierarchy of each file, with each model's type, local transform, and the
+/// geometry and materials attached to it.
+pub fn run(args: &clap::ArgMatches) -> anyhow::Result<()> {
+    let files: Vec<&Path> = args.values_of("files").unwrap().map(Path::new).collect();
+    for (i, path) in files.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_tree(path).with_context(|| format!("Could not read fbx file {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// The objects of a file, and how they are connected.
+struct Scene<'a> {
+    objects: HashMap<i64, &'a Node>,
+    /// The children of each model, in file order. Root models are the children of 0.
+    children: HashMap<i64, Vec<i64>>,
+    /// The objects (geometry, materials, ...) connected to each model.
+    attached: HashMap<i64, Vec<i64>>,
+    /// The default properties of models, from `Definitions`.
+    template: Option<&'a Node>,
+}
+
+fn print_tree(path: &Path) -> anyhow::Result<()> {
+    let (bytes, _) = read_as_binary(path)?;
+    let (_, doc) = parse_document(&bytes)?;
+    let root = Node::from_handle(&doc.tree().root());
+
+    let objects: HashMap<i64, &Node> = root
+        .child("Objects")
+        .map(|objects| {
+            objects
+                .children
+                .iter()
+                .filter_map(|o| Some((o.object_id()?, o)))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Models are listed in file order, which is the order they have in the modeling program.
+    let order: HashMap<i64, usize> = root
+        .child("Objects")
+        .map(|objects| {
+            objects
+                .children
+                .iter()
+                .enumerate()
+                .filter_map(|(i, o)| Some((o.object_id()?, i)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let in_file_order = |ids: &mut Vec<i64>| ids.sort_by_key(|id| order.get(id).copied());
+
+    // Anything else connected to a model (geometry, materials, attributes) is attached to it.
+    let mut children: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut attached: HashMap<i64, Vec<i64>> = HashMap::new();
+    for model in get_models(&doc) {
+        let id = model.object_id().raw();
+        if let Some(parent) = model.parent_model() {
+            children
+                .entry(parent.object_id().raw())
+                .or_default()
+                .push(id);
+        }
+        let sources = model
+            .source_objects()
+            .filter(|o| o.label().is_none())
+            .filter_map(|o| o.object_handle())
+            .filter(|o| o.class() != "Model")
+            .map(|o| o.object_id().raw())
+            .filter(|id| objects.contains_key(id));
+        attached.entry(id).or_default().extend(sources);
+    }
+    for ids in children.values_mut().chain(attached.values_mut()) {
+        in_file_order(ids);
+    }
+    // Root models are the children of 0.
+    let mut roots: Vec<i64> = get_model_roots(&doc)
+        .iter()
+        .map(|m| m.object_id().raw())
+        .collect();
+    in_file_order(&mut roots);
+    children.insert(0, roots);
+
+    let template = root
+        .child("Definitions")
+        .into_iter()
+        .flat_map(|d| d.children_by_name("ObjectType"))
+        .filter(|t| t.attributes.get(0).and_then(AttributeValue::get_string) == Some("Model"))
+        .flat_map(|t| t.children_by_name("PropertyTemplate"))
+        .next();
+    let scene = Scene {
+        objects,
+        children,
+        attached,
+        template,
+    };
+
+    println!("{}", path.display());
+    let roots = scene.children.get(&0).cloned().unwrap_or_default();
+    if roots.is_empty() {
+        println!("(no models)");
+    }
+    let mut visited = HashSet::new();
+    for (i, &model) in roots.iter().enumerate() {
+        print_model(&scene, model, "", i + 1 == roots.len(), &mut visited);
+    }
+
+    Ok(())
+}
+
+fn print_model(scene: &Scene, id: i64, prefix: &str, last: bool, visited: &mut HashSet<i64>) {
+    // A (broken) file could connect a model to its own descendant.
+    if !visited.insert(id) {
+        return;
+    }
+    let model = scene.objects[&id];
+    let (branch, indent) = if last {
+        ("└── ", "    ")
+    } else {
+        ("├── ", "│   ")
+    };
+    let children = scene.children.get(&id).cloned().unwrap_or_default();
+    // Details line up under the model's name, and continue the branch to its children.
+    let details = format!(
+        "{}{}{}",
+        prefix,
+        indent,
+        if children.is_empty() {
+            "    "
+        } else {
+            "│   "
+        }
+    );
+
+    println!(
+        "{}{}{} ({})",
+        prefix,
+        branch,
+        model.object_name().unwrap_or("(no name)"),
+        model_type(model.object_subclass().unwrap_or(""))
+    );
+
+    let vector = |name: &str| property_vector(model, scene.template, name);
+    let t = vector("Lcl Translation").unwrap_or([0.0; 3]);
+    let r = vector("Lcl Rotation").unwrap_or([0.0; 3]);
+    let s = vector("Lcl Scaling").unwrap_or([1.0; 3]);
+    println!(
+        "{}translation: {}  rotation: {}  scale: {}",
+        details,
+        format_vector(t),
+        format_vector(r),
+        format_vector(s)
+    );
+
+    // Pre and post rotations are usually zero, and are only shown when they aren't.
+    let non_zero = |v: &[f64; 3]| v.iter().any(|&x| x != 0.0);
+    let pre = vector("PreRotation").filter(non_zero);
+    let post = vector("PostRotation").filter(non_zero);
+    if pre.is_some() || post.is_some() {
+        println!(
+            "{}pre-rotation: {}  post-rotation: {}",
+            details,
+            format_vector(pre.unwrap_or([0.0; 3])),
+            format_vector(post.unwrap_or([0.0; 3]))
+        );
+    }
+
+    let attached: Vec<&Node> = scene
+        .attached
+        .get(&id)
+        .map(|ids| ids.iter().map(|id| scene.objects[id]).collect())
+        .unwrap_or_default();
+    for geometry in attached.iter().filter(|o| o.name == "Geometry") {
+        println!(
+            "{}geometry: {} ({})",
+            details,
+            object_name(geometry),
+            geometry
+                .object_subclass()
+                .unwrap_or("unknown")
+                .to_lowercase()
+        );
+    }
+    let materials: Vec<String> = attached
+        .iter()
+        .filter(|o| o.name == "Material")
+        .map(|m| object_name(m).to_owned())
+        .collect();
+    if !materials.is_empty() {
+        println!("{}materials: {}", details, materials.join(", "));
+    }
+
+    let child_prefix = format!("{}{}", prefix, indent);
+    for (i, &child) in children.iter().enumerate() {
+        print_model(
+            scene,
+            child,
+            &child_prefix,
+            i + 1 == children.len(),
+            visited,
+        );
+    }
+}
+
+fn object_name(object: &Node) -> &str {
+    match object.object_name() {
+        Some(name) if !name.is_empty() => name,
+        _ => "(no name)",
+    }
+}
+
+/// Reads a vector property of a model. Properties that aren't set on the model have the default
+/// value from the `Definitions` template.
+fn property_vector(model: &Node, template: Option<&Node>, name: &str) -> Option<[f64; 3]> {
+    let values = model
+        .property_values(name)
+        .or_else(|| template?.property_values(name))?;
+    if values.len() < 3 {
+        return None;
+    }
+
+    let mut vector = [0.0; 3];
+    for (v, value) in vector.iter_mut().zip(values) {
+        *v = match value {
+            AttributeValue::F64(x) => *x,
+            AttributeValue::F32(x) => f64::from(*x),
+            _ => return None,
+        };
+    }
+    Some(vector)
+}
+
+fn format_vector(v: [f64; 3]) -> String {
+    format!("({}, {}, {})", v[0], v[1], v[2])
+}
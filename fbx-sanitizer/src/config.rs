@@ -0,0 +1,271 @@
+use crate::utils::glob_set;
+use anyhow::Context;
+use globset::GlobSet;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Name of the project configuration file. It is looked up in the working directory and every
+/// parent directory. Like `.editorconfig`, it is also looked up in the directory of each fbx file
+/// and its parents, and files closer to the fbx file take precedence.
+pub const CONFIG_FILE_NAME: &str = "fbx-sanitizer.toml";
+
+/// Rules that apply before any config file. Config files can override these like any other setting.
+const DEFAULT_CONFIG: &str = r#"
+# High poly meshes in Raw~ are only used for baking, and don't need to be triangulated.
+[[overrides]]
+paths = ["**/Raw~/**/*_HP.[fF][bB][xX]"]
+
+[overrides.checks.no_quads]
+enabled = false
+"#;
+
+/// Project configuration, read from `fbx-sanitizer.toml`.
+///
+/// ```toml
+/// [checks.bounding_box_size]
+/// enabled = true
+/// severity = "warning"
+/// max_bound_size = 500.0
+/// ```
+///
+/// Each table under `checks` is keyed by a check id (see `--list-checks`). Besides `enabled` and
+/// `severity`, which apply to every check, the keys are the settings of that check's struct.
+///
+/// `overrides` change the settings for files matching a set of globs, relative to the directory
+/// of the config file:
+///
+/// ```toml
+/// [[overrides]]
+/// paths = ["Environment/Terrain/**"]
+///
+/// [overrides.checks.bounding_box_size]
+/// max_bound_size = 5000.0
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Stops the search for config files in parent directories.
+    #[serde(default)]
+    pub root: bool,
+    #[serde(default)]
+    pub checks: toml::value::Table,
+    #[serde(default)]
+    pub overrides: Vec<Override>,
+}
+
+/// Check settings that only apply to files matching `paths`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    /// Globs, relative to the directory of the config file. Globs without a `/` match files in
+    /// any subdirectory, ie. `*_HP.fbx`.
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub checks: toml::value::Table,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> anyhow::Result<Config> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read config file [{}]", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("Could not parse config file [{}]", path.display()))
+    }
+
+    /// Merges `settings` into the check settings of this config. Settings are merged per check,
+    /// so only the keys in `settings` change.
+    fn merge_checks(&mut self, settings: &toml::value::Table) -> anyhow::Result<()> {
+        for (id, value) in settings {
+            let table = match value {
+                toml::Value::Table(table) => table,
+                _ => anyhow::bail!("Settings for check [{}] must be a table.", id),
+            };
+
+            let merged = self
+                .checks
+                .entry(id.clone())
+                .or_insert_with(|| toml::Value::Table(Default::default()));
+            if let toml::Value::Table(merged) = merged {
+                for (key, value) in table {
+                    merged.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The settings table for a single check, if the config has one.
+    pub fn check_settings(&self, id: &str) -> anyhow::Result<Option<&toml::value::Table>> {
+        match self.checks.get(id) {
+            None => Ok(None),
+            Some(toml::Value::Table(table)) => Ok(Some(table)),
+            Some(_) => anyhow::bail!("Settings for check [{}] must be a table.", id),
+        }
+    }
+}
+
+/// Deserializes a list of regex patterns, failing on the first invalid pattern.
+pub fn deserialize_regexes<'de, D>(deserializer: D) -> Result<Vec<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|p| Regex::new(p).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// Deserializes a single regex pattern.
+pub fn deserialize_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Regex::new(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// A loaded config file, with its overrides' globs compiled.
+struct ConfigFile {
+    /// The directory override paths are relative to. None for the built-in defaults, whose paths
+    /// are matched against the whole path.
+    dir: Option<PathBuf>,
+    config: Config,
+    globs: Vec<GlobSet>,
+}
+
+impl ConfigFile {
+    fn new(dir: Option<PathBuf>, config: Config) -> anyhow::Result<ConfigFile> {
+        let globs = config
+            .overrides
+            .iter()
+            .map(|o| glob_set(&o.paths))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(ConfigFile { dir, config, globs })
+    }
+
+    /// Merges the settings of this file that apply to `file` into `config`.
+    fn apply(&self, file: &Path, config: &mut Config) -> anyhow::Result<()> {
+        config.merge_checks(&self.config.checks)?;
+
+        let relative = match &self.dir {
+            Some(dir) => match file.strip_prefix(dir) {
+                Ok(relative) => relative,
+                Err(_) => return Ok(()),
+            },
+            None => file,
+        };
+
+        for (o, globs) in self.config.overrides.iter().zip(&self.globs) {
+            if globs.is_match(relative) {
+                config.merge_checks(&o.checks)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the settings that apply to each fbx file.
+///
+/// Settings are merged from the built-in defaults, then the project config files found from the
+/// working directory upward, then the config files between the project and the fbx file, from the
+/// outermost directory to the directory of the fbx file. The search stops at a config with
+/// `root = true`. Within a config file, overrides are applied in order after its `checks`.
+pub struct ConfigResolver {
+    defaults: ConfigFile,
+    /// Set when a config file is passed on the command line. No other config files are used then.
+    explicit: Option<ConfigFile>,
+    /// The working directory, where the search for project config files starts.
+    working_dir: PathBuf,
+    /// Config files found so far, by directory.
+    cache: HashMap<PathBuf, Option<Rc<ConfigFile>>>,
+}
+
+impl ConfigResolver {
+    pub fn new(explicit: Option<&Path>) -> anyhow::Result<ConfigResolver> {
+        let defaults = ConfigFile::new(None, toml::from_str(DEFAULT_CONFIG)?)?;
+        let explicit = match explicit {
+            Some(path) => {
+                let path = path
+                    .canonicalize()
+                    .with_context(|| format!("Could not find config file [{}]", path.display()))?;
+                let dir = path.parent().map(Path::to_owned);
+                Some(ConfigFile::new(dir, Config::load(&path)?)?)
+            }
+            None => None,
+        };
+
+        let working_dir = std::env::current_dir()
+            .and_then(|dir| dir.canonicalize())
+            .context("Could not find the working directory")?;
+
+        Ok(ConfigResolver {
+            defaults,
+            explicit,
+            working_dir,
+            cache: HashMap::new(),
+        })
+    }
+
+    /// Returns the merged config for the fbx file at `file`.
+    pub fn resolve(&mut self, file: &Path) -> anyhow::Result<Config> {
+        let file = file
+            .canonicalize()
+            .with_context(|| format!("Could not find [{}]", file.display()))?;
+        let mut config = Config::default();
+        self.defaults.apply(&file, &mut config)?;
+
+        if let Some(explicit) = &self.explicit {
+            explicit.apply(&file, &mut config)?;
+            return Ok(config);
+        }
+
+        // Collect config files from the fbx file outwards, until the path joins the working
+        // directory's. The project config files are collected from the working directory outwards
+        // after them. Then they are all applied in reverse.
+        let working_dir = self.working_dir.clone();
+        let fbx_dirs = file
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| !working_dir.starts_with(dir));
+        let mut files = vec![];
+        for dir in fbx_dirs.chain(working_dir.ancestors()) {
+            if let Some(config_file) = self.load_dir(dir)? {
+                let is_root = config_file.config.root;
+                files.push(config_file);
+                if is_root {
+                    break;
+                }
+            }
+        }
+
+        for config_file in files.iter().rev() {
+            config_file.apply(&file, &mut config)?;
+        }
+
+        Ok(config)
+    }
+
+    fn load_dir(&mut self, dir: &Path) -> anyhow::Result<Option<Rc<ConfigFile>>> {
+        if let Some(cached) = self.cache.get(dir) {
+            return Ok(cached.clone());
+        }
+
+        let path = dir.join(CONFIG_FILE_NAME);
+        let config_file = if path.is_file() {
+            Some(Rc::new(ConfigFile::new(
+                Some(dir.to_owned()),
+                Config::load(&path)?,
+            )?))
+        } else {
+            None
+        };
+
+        self.cache.insert(dir.to_owned(), config_file.clone());
+        Ok(config_file)
+    }
+}
@@ -0,0 +1,125 @@
+use fbxcel_dom::v7400::object::{ObjectHandle, ObjectId};
+use serde::Serialize;
+
+/// A single issue found by a check.
+///
+/// Besides the human readable message, a diagnostic records which object it is about and the
+/// values that were found, so reporters and fixers don't need to parse the message.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Id of the check that produced this diagnostic. This is filled in by the check runner, so
+    /// checks don't need to set it themselves.
+    pub check_id: &'static str,
+    /// Checks usually leave this unset, and the check runner fills it in from the check's
+    /// severity. A check can set it to report some issues more or less severely than others.
+    pub severity: Option<Severity>,
+    pub message: String,
+    /// The object the issue was found on. None for file-level issues, like global settings.
+    pub object: Option<ObjectRef>,
+    /// The offending value, as found in the file.
+    pub measured: Option<String>,
+    /// The value the check expected instead.
+    pub expected: Option<String>,
+}
+
+/// Identifies an object in the fbx document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectRef {
+    pub id: ObjectId,
+    /// The name shown to the user. For geometry, this is usually the name of the model that uses it.
+    /// See `utils::geo_name`.
+    pub name: String,
+    /// The object's node name, ie. "Model" or "Geometry".
+    pub class: String,
+    /// The object's type, ie. "Mesh" or "LimbNode".
+    pub subclass: String,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            check_id: "",
+            severity: None,
+            message: message.into(),
+            object: None,
+            measured: None,
+            expected: None,
+        }
+    }
+
+    /// Attaches the object the issue was found on. The name is passed separately, since the
+    /// object's own name is not always the most useful one.
+    pub fn with_object(mut self, obj: &ObjectHandle<'_>, name: &str) -> Diagnostic {
+        self.object = Some(ObjectRef {
+            id: obj.object_id(),
+            name: name.to_owned(),
+            class: obj.class().to_owned(),
+            subclass: obj.subclass().to_owned(),
+        });
+        self
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Diagnostic {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn measured(mut self, value: impl ToString) -> Diagnostic {
+        self.measured = Some(value.to_string());
+        self
+    }
+
+    pub fn expected(mut self, value: impl ToString) -> Diagnostic {
+        self.expected = Some(value.to_string());
+        self
+    }
+
+    /// The severity of this diagnostic. Unset severities are treated as errors.
+    pub fn severity(&self) -> Severity {
+        self.severity.unwrap_or(Severity::Error)
+    }
+}
+
+/// How serious an issue found by a check is.
+///
+/// Only errors cause a non-zero exit code. Warnings are printed, and can be promoted to errors
+/// with `--deny-warnings` or limited with `--max-warnings`. Info is purely informational.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" | "warn" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => anyhow::bail!(
+                "Unknown severity [{}]. Expected one of: error, warning, info.",
+                s
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
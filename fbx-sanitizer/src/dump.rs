@@ -0,0 +1,198 @@
+use crate::ascii::read_as_binary;
+use crate::tree::Node;
+use crate::utils::parse_document;
+use anyhow::bail;
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+/// The formats `--dump-structure` can write.
+pub const FORMATS: &[&str] = &["yaml", "json"];
+
+/// Arrays longer than this are cut to their first `ARRAY_LIMIT` values, followed by a summary.
+const ARRAY_LIMIT: usize = 16;
+
+#[derive(Serialize)]
+struct Dump<'a> {
+    file: String,
+    fbx_version: u32,
+    nodes: Vec<DumpNode<'a>>,
+}
+
+#[derive(Serialize)]
+struct DumpNode<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attributes: Vec<DumpValue>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<DumpNode<'a>>,
+}
+
+/// An attribute, with its binary type, ie. "i64" or "f64[]".
+#[derive(Serialize)]
+struct DumpValue {
+    #[serde(rename = "type")]
+    value_type: &'static str,
+    value: serde_json::Value,
+    /// The full length of arrays and binary data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length: Option<usize>,
+    /// True if `value` only has the first `ARRAY_LIMIT` values.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    /// The range of a truncated array of numbers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+}
+
+/// Writes the node tree of an fbx file for `--dump-structure`, in the format given by
+/// `--dump-format`. Only the nodes at `--path` are written, if given.
+///
+/// The dump is written to `--dump-output` (`-` for stdout), or next to the file as
+/// `<file>_structure.yml` (or `.json`).
+pub fn write_structure(file: &Path, args: &clap::ArgMatches) -> anyhow::Result<()> {
+    let format = args.value_of("dump-format").unwrap_or("yaml");
+    let text = dump_structure(file, format, args.value_of("path"))?;
+
+    match args.value_of("dump-output") {
+        Some("-") => {
+            let stdout = std::io::stdout();
+            let mut stdout = stdout.lock();
+            stdout.write_all(text.as_bytes())?;
+            stdout.flush()?;
+        }
+        Some(output) => std::fs::write(output, text)?,
+        None => {
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = if format == "json" { "json" } else { "yml" };
+            let output = file.with_file_name(format!("{}_structure.{}", stem, extension));
+            std::fs::write(&output, text)?;
+            log::info!(
+                "Dumped {} structure to {}",
+                file.display(),
+                output.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Dumps the node tree of an fbx file as YAML or JSON. `path` is a filter like
+/// `"Objects/Geometry"`: only the nodes at that path, and their children, are dumped.
+pub fn dump_structure(file: &Path, format: &str, path: Option<&str>) -> anyhow::Result<String> {
+    let (bytes, _) = read_as_binary(file)?;
+    let (version, doc) = parse_document(&bytes)?;
+    let root = Node::from_handle(&doc.tree().root());
+
+    let nodes = match path {
+        Some(path) => {
+            let nodes = find(&root, path);
+            if nodes.is_empty() {
+                bail!("No nodes found at path [{}].", path);
+            }
+            nodes
+        }
+        None => root.children.iter().collect(),
+    };
+    let dump = Dump {
+        file: file.display().to_string(),
+        fbx_version: version.raw(),
+        nodes: nodes.into_iter().map(dump_node).collect(),
+    };
+
+    Ok(match format {
+        "json" => serde_json::to_string_pretty(&dump)? + "\n",
+        _ => serde_yaml::to_string(&dump)?,
+    })
+}
+
+/// Finds the nodes at a path of node names, ie. "Objects/Geometry" finds every geometry.
+fn find<'a>(root: &'a Node, path: &'a str) -> Vec<&'a Node> {
+    let mut nodes = vec![root];
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        nodes = nodes
+            .iter()
+            .flat_map(|node| node.children_by_name(name))
+            .collect();
+    }
+    nodes
+}
+
+fn dump_node(node: &Node) -> DumpNode<'_> {
+    DumpNode {
+        name: &node.name,
+        attributes: node.attributes.iter().map(dump_value).collect(),
+        children: node.children.iter().map(dump_node).collect(),
+    }
+}
+
+fn dump_value(value: &AttributeValue) -> DumpValue {
+    let scalar = |value_type, value| DumpValue {
+        value_type,
+        value,
+        length: None,
+        truncated: false,
+        min: None,
+        max: None,
+    };
+
+    match value {
+        AttributeValue::Bool(v) => scalar("bool", (*v).into()),
+        AttributeValue::I16(v) => scalar("i16", (*v).into()),
+        AttributeValue::I32(v) => scalar("i32", (*v).into()),
+        AttributeValue::I64(v) => scalar("i64", (*v).into()),
+        AttributeValue::F32(v) => scalar("f32", (*v).into()),
+        AttributeValue::F64(v) => scalar("f64", (*v).into()),
+        AttributeValue::String(v) => scalar("string", v.as_str().into()),
+        AttributeValue::ArrBool(v) => array("bool[]", v, None),
+        AttributeValue::ArrI32(v) => array("i32[]", v, Some(|x: &i32| f64::from(*x))),
+        AttributeValue::ArrI64(v) => array("i64[]", v, Some(|x: &i64| *x as f64)),
+        AttributeValue::ArrF32(v) => array("f32[]", v, Some(|x: &f32| f64::from(*x))),
+        AttributeValue::ArrF64(v) => array("f64[]", v, Some(|x: &f64| *x)),
+        // Binary data is written as hex.
+        AttributeValue::Binary(v) => {
+            let hex: String = v
+                .iter()
+                .take(ARRAY_LIMIT)
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            DumpValue {
+                value_type: "binary",
+                value: hex.into(),
+                length: Some(v.len()),
+                truncated: v.len() > ARRAY_LIMIT,
+                min: None,
+                max: None,
+            }
+        }
+    }
+}
+
+/// Dumps an array, cut to `ARRAY_LIMIT` values. Arrays of numbers that are cut also get their
+/// range, using `to_f64`.
+fn array<T: Serialize>(
+    value_type: &'static str,
+    values: &[T],
+    to_f64: Option<fn(&T) -> f64>,
+) -> DumpValue {
+    let truncated = values.len() > ARRAY_LIMIT;
+    let (min, max) = match to_f64 {
+        Some(to_f64) if truncated => (
+            Some(values.iter().map(to_f64).fold(f64::INFINITY, f64::min)),
+            Some(values.iter().map(to_f64).fold(f64::NEG_INFINITY, f64::max)),
+        ),
+        _ => (None, None),
+    };
+
+    DumpValue {
+        value_type,
+        value: serde_json::to_value(&values[..values.len().min(ARRAY_LIMIT)]).unwrap_or_default(),
+        length: Some(values.len()),
+        truncated,
+        min,
+        max,
+    }
+}
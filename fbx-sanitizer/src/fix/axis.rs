@@ -0,0 +1,410 @@
+use crate::checks::correct_coordinate_axis::CoordinateAxis;
+use crate::tree::{connections, Node};
+use cgmath::{Deg, Matrix3, Rad};
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+
+/// Model properties that are positions or directions.
+const VECTOR_PROPERTIES: &[&str] = &[
+    "Lcl Translation",
+    "RotationOffset",
+    "RotationPivot",
+    "ScalingOffset",
+    "ScalingPivot",
+    "GeometricTranslation",
+];
+
+/// Model properties that are a scale per axis.
+const SCALE_PROPERTIES: &[&str] = &["Lcl Scaling", "GeometricScaling"];
+
+/// Model properties that are euler rotations, always applied in XYZ order.
+const XYZ_ROTATION_PROPERTIES: &[&str] = &["PreRotation", "PostRotation", "GeometricRotation"];
+
+/// Layer elements holding one direction per vertex, and the name of their array.
+const DIRECTION_LAYERS: &[(&str, &str)] = &[
+    ("LayerElementNormal", "Normals"),
+    ("LayerElementBinormal", "Binormals"),
+    ("LayerElementTangent", "Tangents"),
+];
+
+/// Cluster and pose matrices, which are 4x4 transforms in scene space.
+const MATRICES: &[&str] = &[
+    "Transform",
+    "TransformLink",
+    "TransformAssociateModel",
+    "Matrix",
+];
+
+/// The application order of the axes of each `RotationOrder`, ie. `[0, 1, 2]` for eEulerXYZ.
+const ROTATION_ORDERS: &[[usize; 3]] = &[
+    [0, 1, 2],
+    [0, 2, 1],
+    [1, 2, 0],
+    [1, 0, 2],
+    [2, 0, 1],
+    [2, 1, 0],
+];
+
+const CHANNELS: &[&str] = &["d|X", "d|Y", "d|Z"];
+
+/// A change of basis from one axis system to another.
+///
+/// Both systems must have the same handedness, so the conversion is a rotation that maps each
+/// axis onto another axis, possibly negated. ie. converting Z-up to Y-up maps Z to Y, and Y to -Z.
+pub struct AxisConversion {
+    /// For each old axis (X, Y, Z), the new axis it maps to and its sign.
+    axes: [(usize, f64); 3],
+}
+
+impl AxisConversion {
+    /// Returns None if the axis systems have a different handedness, since converting between
+    /// them would mirror the scene.
+    pub fn new(from: &CoordinateAxis, to: &CoordinateAxis) -> Option<AxisConversion> {
+        // Each axis system maps file coordinates to (right, up, front). The conversion is
+        // `to^T * from`: from the old file coordinates, to (right, up, front), to the new ones.
+        let rows = |axis: &CoordinateAxis| {
+            [axis.coord, axis.up, axis.front]
+                .iter()
+                .map(|v| [i32::from(v.x), i32::from(v.y), i32::from(v.z)])
+                .collect::<Vec<_>>()
+        };
+        let (from, to) = (rows(from), rows(to));
+
+        let mut m = [[0i32; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|r| to[r][i] * from[r][j]).sum();
+            }
+        }
+
+        let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+        if det != 1 {
+            return None;
+        }
+
+        let mut axes = [(0, 1.0); 3];
+        for (j, axis) in axes.iter_mut().enumerate() {
+            let i = (0..3).find(|&i| m[i][j] != 0)?;
+            *axis = (i, f64::from(m[i][j]));
+        }
+        Some(AxisConversion { axes })
+    }
+
+    /// Converts a position or direction.
+    fn vector(&self, v: [f64; 3]) -> [f64; 3] {
+        let mut out = [0.0; 3];
+        for (j, (i, sign)) in self.axes.iter().enumerate() {
+            out[*i] = sign * v[j];
+        }
+        out
+    }
+
+    /// Converts a scale per axis. Scales only move between axes, since `-1 * s * -1 = s`.
+    fn scale(&self, s: [f64; 3]) -> [f64; 3] {
+        let mut out = [0.0; 3];
+        for (j, (i, _)) in self.axes.iter().enumerate() {
+            out[*i] = s[j];
+        }
+        out
+    }
+
+    /// Converts a 4x4 column-major transform `M` to `P * M * P^T`.
+    fn matrix(&self, m: &[f64]) -> Vec<f64> {
+        let axes = [self.axes[0], self.axes[1], self.axes[2], (3, 1.0)];
+        let mut out = vec![0.0; 16];
+        for (col, (new_col, col_sign)) in axes.iter().enumerate() {
+            for (row, (new_row, row_sign)) in axes.iter().enumerate() {
+                out[new_col * 4 + new_row] = row_sign * col_sign * m[col * 4 + row];
+            }
+        }
+        out
+    }
+
+    /// Converts an euler rotation in the given `RotationOrder`. Rotating about an axis becomes
+    /// rotating about the axis it maps to, so the angles move between axes, and the order changes.
+    fn euler(&self, angles: [f64; 3], order: usize) -> ([f64; 3], usize) {
+        // eSphericXYZ is converted like eEulerXYZ.
+        let order = *ROTATION_ORDERS.get(order).unwrap_or(&ROTATION_ORDERS[0]);
+        let new_order = [
+            self.axes[order[0]].0,
+            self.axes[order[1]].0,
+            self.axes[order[2]].0,
+        ];
+        let new_order = ROTATION_ORDERS
+            .iter()
+            .position(|o| *o == new_order)
+            .expect("a permutation of the axes is always a rotation order");
+        (self.vector(angles), new_order)
+    }
+
+    /// Converts an euler rotation applied in XYZ order, keeping the order. This goes through a
+    /// matrix, so it can't be used for animated rotations.
+    fn euler_xyz(&self, angles: [f64; 3]) -> [f64; 3] {
+        let rotation: Matrix3<f64> = Matrix3::from_angle_z(Deg(angles[2]))
+            * Matrix3::from_angle_y(Deg(angles[1]))
+            * Matrix3::from_angle_x(Deg(angles[0]));
+
+        let mut m = [0.0; 16];
+        for (col, column) in [rotation.x, rotation.y, rotation.z].iter().enumerate() {
+            m[col * 4..col * 4 + 3].copy_from_slice(&[column.x, column.y, column.z]);
+        }
+        let m = self.matrix(&m);
+        // Row-major access into the column-major matrix.
+        let r = |row: usize, col: usize| m[col * 4 + row];
+
+        let y = (-r(2, 0)).max(-1.0).min(1.0).asin();
+        let (x, z) = if r(2, 0).abs() < 1.0 - 1e-9 {
+            (r(2, 1).atan2(r(2, 2)), r(1, 0).atan2(r(0, 0)))
+        } else {
+            // Gimbal lock. Put the whole rotation on X.
+            ((-r(1, 2)).atan2(r(1, 1)), 0.0)
+        };
+
+        let deg = |a: f64| Deg::from(Rad(a)).0;
+        [deg(x), deg(y), deg(z)]
+    }
+
+    /// Converts the axis of an animation channel, ie. "d|Z" to "d|Y".
+    fn channel(&self, channel: &str) -> Option<(&'static str, f64)> {
+        let j = CHANNELS.iter().position(|c| *c == channel)?;
+        let (i, sign) = self.axes[j];
+        Some((CHANNELS[i], sign))
+    }
+}
+
+/// Converts the whole scene to another axis system, keeping it the same in world space.
+///
+/// Positions and directions are rotated. Every model's local transform `T` becomes
+/// `P * T * P^T`, so root objects without a rotation still don't have one. For euler rotations,
+/// this moves the angles between axes and changes the model's `RotationOrder`. Animation curves
+/// are moved to the channel of their new axis, and negated if the axis flipped.
+///
+/// Cameras and lights look down their local X axis, which isn't adjusted.
+pub fn convert(tree: &mut Node, conversion: &AxisConversion) {
+    let connections = connections(tree);
+
+    // Which kind of property each curve node animates.
+    let animated_property = |id: i64| {
+        connections
+            .iter()
+            .find(|c| c.kind == "OP" && c.child == id)
+            .and_then(|c| c.property.clone())
+    };
+
+    if let Some(objects) = tree.child_mut("Objects") {
+        for object in &mut objects.children {
+            match object.name.as_str() {
+                "Geometry" => convert_geometry(object, conversion),
+                "Model" => convert_model(object, conversion),
+                "Deformer" | "Pose" => convert_matrices(object, conversion),
+                "AnimationCurveNode" => {
+                    let property = object.object_id().and_then(animated_property);
+                    convert_curve_node(object, property.as_deref(), conversion);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Move each curve to the channel of its new axis, and negate it if the axis flipped.
+    let mut negated = vec![];
+    if let Some(node) = tree.child_mut("Connections") {
+        for c in node.children_by_name_mut("C") {
+            let (child, parent) = match (c.attributes.get(1), c.attributes.get(2)) {
+                (Some(AttributeValue::I64(child)), Some(AttributeValue::I64(parent))) => {
+                    (*child, *parent)
+                }
+                _ => continue,
+            };
+            let property = animated_property(parent);
+            let property = match property.as_deref() {
+                Some(p) if is_vector(p) || is_scale(p) || p == "Lcl Rotation" => p,
+                _ => continue,
+            };
+
+            if let Some(AttributeValue::String(channel)) = c.attributes.get_mut(3) {
+                if let Some((new_channel, sign)) = conversion.channel(channel) {
+                    *channel = new_channel.to_owned();
+                    if sign < 0.0 && !is_scale(property) {
+                        negated.push(child);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(objects) = tree.child_mut("Objects") {
+        for curve in objects.children_by_name_mut("AnimationCurve") {
+            if curve.object_id().map_or(false, |id| negated.contains(&id)) {
+                negate_curve(curve);
+            }
+        }
+    }
+}
+
+fn is_vector(property: &str) -> bool {
+    VECTOR_PROPERTIES.contains(&property)
+}
+
+fn is_scale(property: &str) -> bool {
+    SCALE_PROPERTIES.contains(&property)
+}
+
+/// Reads the 3 values of a vector property.
+fn read_vector(node: &Node, name: &str) -> Option<[f64; 3]> {
+    match node.property_values(name)? {
+        [AttributeValue::F64(x), AttributeValue::F64(y), AttributeValue::F64(z), ..] => {
+            Some([*x, *y, *z])
+        }
+        _ => None,
+    }
+}
+
+fn write_vector(node: &mut Node, name: &str, v: [f64; 3]) {
+    if let Some(p) = node.property_mut(name) {
+        for (attribute, value) in p.attributes.iter_mut().skip(4).zip(&v) {
+            *attribute = AttributeValue::F64(*value);
+        }
+    }
+}
+
+fn convert_model(model: &mut Node, conversion: &AxisConversion) {
+    for name in VECTOR_PROPERTIES {
+        if let Some(v) = read_vector(model, name) {
+            write_vector(model, name, conversion.vector(v));
+        }
+    }
+    for name in SCALE_PROPERTIES {
+        if let Some(s) = read_vector(model, name) {
+            write_vector(model, name, conversion.scale(s));
+        }
+    }
+    for name in XYZ_ROTATION_PROPERTIES {
+        if let Some(r) = read_vector(model, name) {
+            write_vector(model, name, conversion.euler_xyz(r));
+        }
+    }
+
+    let order = match model.property_values("RotationOrder") {
+        Some([AttributeValue::I32(order), ..]) if *order >= 0 && *order < 6 => *order as usize,
+        _ => 0,
+    };
+    let rotation = read_vector(model, "Lcl Rotation").unwrap_or([0.0; 3]);
+    let (rotation, new_order) = conversion.euler(rotation, order);
+    if model.property("Lcl Rotation").is_some() {
+        write_vector(model, "Lcl Rotation", rotation);
+    }
+    if new_order != order {
+        model.set_property(
+            "RotationOrder",
+            "enum",
+            "",
+            vec![AttributeValue::I32(new_order as i32)],
+        );
+    }
+}
+
+/// Converts every array of 3D values in `node` (ie. `Vertices`) in place.
+fn convert_array(node: &mut Node, f: impl Fn([f64; 3]) -> [f64; 3]) {
+    if let Some(AttributeValue::ArrF64(values)) = node.attributes.get_mut(0) {
+        for v in values.chunks_exact_mut(3) {
+            v.copy_from_slice(&f([v[0], v[1], v[2]]));
+        }
+    }
+}
+
+fn convert_geometry(geometry: &mut Node, conversion: &AxisConversion) {
+    // Meshes and blend shapes store their vertices directly. Blend shapes also store normals.
+    for name in &["Vertices", "Normals"] {
+        if let Some(array) = geometry.child_mut(name) {
+            convert_array(array, |v| conversion.vector(v));
+        }
+    }
+
+    for (layer, array) in DIRECTION_LAYERS {
+        for element in geometry.children_by_name_mut(layer) {
+            if let Some(array) = element.child_mut(array) {
+                convert_array(array, |v| conversion.vector(v));
+            }
+        }
+    }
+}
+
+/// Converts cluster matrices, and the matrices of each pose node.
+fn convert_matrices(object: &mut Node, conversion: &AxisConversion) {
+    let convert = |node: &mut Node| {
+        for name in MATRICES {
+            if let Some(matrix) = node.child_mut(name) {
+                if let Some(AttributeValue::ArrF64(m)) = matrix.attributes.get_mut(0) {
+                    if m.len() == 16 {
+                        *m = conversion.matrix(m);
+                    }
+                }
+            }
+        }
+    };
+
+    convert(object);
+    for pose_node in object.children_by_name_mut("PoseNode") {
+        convert(pose_node);
+    }
+}
+
+/// Converts the default values of a curve node, stored as its d|X, d|Y and d|Z properties.
+fn convert_curve_node(node: &mut Node, property: Option<&str>, conversion: &AxisConversion) {
+    let property = match property {
+        Some(p) => p,
+        None => return,
+    };
+
+    let value = |name: &str| match node.property_values(name) {
+        Some([AttributeValue::F64(v), ..]) => Some(*v),
+        _ => None,
+    };
+    let values = match (value("d|X"), value("d|Y"), value("d|Z")) {
+        (Some(x), Some(y), Some(z)) => [x, y, z],
+        _ => return,
+    };
+
+    let converted = if is_vector(property) || property == "Lcl Rotation" {
+        conversion.vector(values)
+    } else if is_scale(property) {
+        conversion.scale(values)
+    } else {
+        return;
+    };
+
+    for (name, value) in CHANNELS.iter().zip(&converted) {
+        if let Some(p) = node.property_mut(name) {
+            if let Some(attribute) = p.attributes.get_mut(4) {
+                *attribute = AttributeValue::F64(*value);
+            }
+        }
+    }
+}
+
+/// Negates the values and slopes of an animation curve.
+fn negate_curve(curve: &mut Node) {
+    for name in &["Default", "KeyValueFloat"] {
+        if let Some(node) = curve.child_mut(name) {
+            for attribute in &mut node.attributes {
+                match attribute {
+                    AttributeValue::F64(v) => *v = -*v,
+                    AttributeValue::ArrF32(values) => values.iter_mut().for_each(|v| *v = -*v),
+                    AttributeValue::ArrF64(values) => values.iter_mut().for_each(|v| *v = -*v),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // The first two floats of each key's attributes are its slopes. See `unit_scale`.
+    if let Some(data) = curve.child_mut("KeyAttrDataFloat") {
+        if let Some(AttributeValue::ArrF32(data)) = data.attributes.get_mut(0) {
+            for key in data.chunks_mut(4) {
+                key.iter_mut().take(2).for_each(|v| *v = -*v);
+            }
+        }
+    }
+}
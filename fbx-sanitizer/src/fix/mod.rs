@@ -0,0 +1,49 @@
+use crate::ascii::read_as_binary;
+use crate::checks::Check;
+use crate::diagnostic::Diagnostic;
+use crate::tree::Node;
+use crate::utils::parse_document;
+use crate::writer::{read_footer_code, write_binary};
+use std::path::Path;
+
+pub mod axis;
+pub mod triangulate;
+pub mod unit_scale;
+
+/// Fixes the issues found in the fbx file at `path`, and writes the result to `output`.
+///
+/// Only checks that found an issue (in `diagnostics`) are asked to fix the file. The file is
+/// copied as-is if nothing was changed. Returns a description of each change, prefixed with the
+/// id of the check that made it.
+pub fn fix_file(
+    path: &Path,
+    output: &Path,
+    checks: &[Box<dyn Check>],
+    diagnostics: &[Diagnostic],
+) -> anyhow::Result<Vec<String>> {
+    // ASCII files are fixed like binary files, and written as binary.
+    let (bytes, _) = read_as_binary(path)?;
+    let (version, doc) = parse_document(&bytes)?;
+
+    let mut tree = Node::from_handle(&doc.tree().root());
+    let mut changes = vec![];
+    for check in checks {
+        if !diagnostics.iter().any(|d| d.check_id == check.id()) {
+            continue;
+        }
+        for change in check.fix(&doc, &mut tree)? {
+            changes.push(format!("[{}] {}", check.id(), change));
+        }
+    }
+
+    if changes.is_empty() {
+        if output != path {
+            std::fs::copy(path, output)?;
+        }
+    } else {
+        let fixed = write_binary(&tree, version.raw(), read_footer_code(&bytes))?;
+        std::fs::write(output, fixed)?;
+    }
+
+    Ok(changes)
+}
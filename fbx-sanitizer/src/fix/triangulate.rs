@@ -0,0 +1,391 @@
+use crate::tree::{connections, Node};
+use crate::utils::polygon_normal;
+use anyhow::{bail, format_err};
+use cgmath::{InnerSpace, Vector3};
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use std::collections::{HashMap, HashSet};
+
+/// Triangulates every mesh that has polygons with more than 3 vertices. Returns a description of
+/// each mesh that was triangulated.
+///
+/// The triangulation is deterministic, so the same file is always triangulated the same way:
+/// - Quads are split along their shortest diagonal, unless that would fold the quad over (when
+///   it is concave). Ties are split from the first vertex.
+/// - Larger polygons are ear clipped: the first convex vertex (in polygon order) whose triangle
+///   contains no other vertex is clipped, until a triangle is left. Polygons without ears, which
+///   are usually self-intersecting, are split at their first convex vertex instead.
+///
+/// Triangles keep the winding of their polygon. Every layer element is remapped to the new
+/// polygon vertices: per polygon-vertex data (ie. normals and UVs) follows its vertex, per polygon
+/// data (ie. materials) is copied to each triangle, and per edge data (ie. smoothing) is kept for
+/// the original edges. New edges inside a polygon are smooth, and have no crease.
+///
+/// A mesh whose layer elements don't match its polygons is left as is, with a warning. Its quads
+/// are still reported by the check.
+pub fn triangulate_meshes(tree: &mut Node) -> Vec<String> {
+    let connections = connections(tree);
+    let objects = match tree.child_mut("Objects") {
+        Some(objects) => objects,
+        None => return vec![],
+    };
+
+    // Geometry is named after its model, like `utils::geo_name`.
+    let model_names: HashMap<i64, String> = objects
+        .children_by_name("Model")
+        .filter_map(|m| Some((m.object_id()?, m.object_name()?.to_owned())))
+        .collect();
+    let geo_name = |geometry: &Node| {
+        let id = geometry.object_id();
+        connections
+            .iter()
+            .filter(|c| c.kind == "OO" && Some(c.child) == id)
+            .find_map(|c| model_names.get(&c.parent))
+            .map(String::as_str)
+            .or_else(|| geometry.object_name())
+            .unwrap_or("(no name)")
+            .to_owned()
+    };
+
+    let mut changes = vec![];
+    for geometry in objects.children_by_name_mut("Geometry") {
+        if geometry
+            .attributes
+            .get(2)
+            .and_then(AttributeValue::get_string)
+            != Some("Mesh")
+        {
+            continue;
+        }
+
+        // Work on a copy, so meshes that can't be triangulated are left untouched.
+        let mut triangulated = geometry.clone();
+        match triangulate_mesh(&mut triangulated) {
+            Ok(Some((polygons, triangles))) => {
+                changes.push(format!(
+                    "Triangulated [{}]: {} polygons into {} triangles.",
+                    geo_name(geometry),
+                    polygons,
+                    triangles
+                ));
+                *geometry = triangulated;
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!(
+                "Could not triangulate mesh [{}]: {:#}",
+                geo_name(geometry),
+                e
+            ),
+        }
+    }
+
+    changes
+}
+
+/// Triangulates a single mesh. Returns the number of polygons before and after, or None if the
+/// mesh was already triangulated.
+fn triangulate_mesh(geometry: &mut Node) -> anyhow::Result<Option<(usize, usize)>> {
+    let positions: Vec<Vector3<f64>> =
+        match geometry.child("Vertices").and_then(|v| v.attributes.get(0)) {
+            Some(AttributeValue::ArrF64(v)) => v
+                .chunks_exact(3)
+                .map(|p| Vector3::new(p[0], p[1], p[2]))
+                .collect(),
+            _ => bail!("Mesh has no vertices."),
+        };
+    let indices = match geometry
+        .child("PolygonVertexIndex")
+        .and_then(|v| v.attributes.get(0))
+    {
+        Some(AttributeValue::ArrI32(v)) => v.clone(),
+        _ => bail!("Mesh has no polygons."),
+    };
+
+    // The control point of each polygon vertex. The last vertex of each polygon is stored as
+    // `!index`.
+    let control_points: Vec<usize> = indices
+        .iter()
+        .map(|&i| (if i < 0 { !i } else { i }) as usize)
+        .collect();
+    if control_points.iter().any(|&cp| cp >= positions.len()) {
+        bail!("Polygon vertex index out of range.");
+    }
+
+    // The polygon vertices of each polygon.
+    let mut polygons = vec![];
+    let mut start = 0;
+    for (i, &index) in indices.iter().enumerate() {
+        if index < 0 {
+            polygons.push(start..i + 1);
+            start = i + 1;
+        }
+    }
+    if start != indices.len() {
+        bail!("Incomplete polygon found.");
+    }
+    if polygons.iter().all(|p| p.len() <= 3) {
+        return Ok(None);
+    }
+
+    // The new polygons, as the old polygon vertex of each of their vertices, and the old polygon
+    // each came from.
+    let mut new_polygons: Vec<Vec<usize>> = vec![];
+    let mut new_polygon_sources = vec![];
+    for (p, polygon) in polygons.iter().enumerate() {
+        if polygon.len() <= 3 {
+            new_polygons.push(polygon.clone().collect());
+            new_polygon_sources.push(p);
+            continue;
+        }
+
+        let points: Vec<Vector3<f64>> = polygon
+            .clone()
+            .map(|pv| positions[control_points[pv]])
+            .collect();
+        for triangle in triangulate_polygon(&points) {
+            new_polygons.push(triangle.iter().map(|&v| polygon.start + v).collect());
+            new_polygon_sources.push(p);
+        }
+    }
+
+    let new_polygon_vertices: Vec<usize> = new_polygons.iter().flatten().copied().collect();
+    let mut new_indices = vec![];
+    for polygon in &new_polygons {
+        for (i, &pv) in polygon.iter().enumerate() {
+            let cp = control_points[pv] as i32;
+            new_indices.push(if i + 1 == polygon.len() { !cp } else { cp });
+        }
+    }
+
+    // Edges are identified by the polygon vertex they start at. Build the new edges in order of
+    // first use, and find the old edge each one was, if any.
+    let edge_key = |polygon: &[usize], i: usize| {
+        let a = control_points[polygon[i]];
+        let b = control_points[polygon[(i + 1) % polygon.len()]];
+        (a.min(b), a.max(b))
+    };
+    let old_edges: Option<Vec<usize>> = match geometry
+        .child("Edges")
+        .and_then(|e| e.attributes.get(0))
+    {
+        Some(AttributeValue::ArrI32(edges)) => Some(edges.iter().map(|&e| e as usize).collect()),
+        _ => None,
+    };
+    let mut old_edge_index = HashMap::new();
+    if let Some(old_edges) = &old_edges {
+        let mut polygon_of = vec![0; indices.len()];
+        for (p, polygon) in polygons.iter().enumerate() {
+            for pv in polygon.clone() {
+                polygon_of[pv] = p;
+            }
+        }
+
+        for (e, &pv) in old_edges.iter().enumerate() {
+            let p = polygon_of
+                .get(pv)
+                .ok_or_else(|| format_err!("Edge index out of range."))?;
+            let polygon: Vec<usize> = polygons[*p].clone().collect();
+            let key = edge_key(&polygon, pv - polygon[0]);
+            old_edge_index.entry(key).or_insert(e);
+        }
+    }
+    let mut new_edges = vec![];
+    let mut new_edge_sources = vec![];
+    let mut seen = HashSet::new();
+    let mut start = 0;
+    for polygon in &new_polygons {
+        for i in 0..polygon.len() {
+            let key = edge_key(polygon, i);
+            if seen.insert(key) {
+                new_edges.push((start + i) as i32);
+                new_edge_sources.push(old_edge_index.get(&key).copied());
+            }
+        }
+        start += polygon.len();
+    }
+
+    // Remap the layer elements.
+    let polygon_vertex_map: Vec<Option<usize>> =
+        new_polygon_vertices.iter().map(|&pv| Some(pv)).collect();
+    let polygon_map: Vec<Option<usize>> = new_polygon_sources.iter().map(|&p| Some(p)).collect();
+    for element in geometry
+        .children
+        .iter_mut()
+        .filter(|c| c.name.starts_with("LayerElement"))
+    {
+        let mapping = element
+            .child("MappingInformationType")
+            .and_then(|m| m.attributes.get(0))
+            .and_then(AttributeValue::get_string)
+            .unwrap_or("")
+            .to_owned();
+        let reference = element
+            .child("ReferenceInformationType")
+            .and_then(|m| m.attributes.get(0))
+            .and_then(AttributeValue::get_string)
+            .unwrap_or("Direct")
+            .to_owned();
+
+        let (map, old_count) = match mapping.as_str() {
+            "ByPolygonVertex" => (&polygon_vertex_map, indices.len()),
+            "ByPolygon" => (&polygon_map, polygons.len()),
+            "ByEdge" => match &old_edges {
+                Some(old_edges) => (&new_edge_sources, old_edges.len()),
+                None => bail!("Per edge layer element without edges."),
+            },
+            // Per control point and uniform data doesn't change.
+            _ => continue,
+        };
+
+        // New edges are smooth and visible. Any other new data is zero.
+        let default = match element.name.as_str() {
+            "LayerElementSmoothing" | "LayerElementVisibility" => 1.0,
+            _ => 0.0,
+        };
+
+        // With IndexToDirect, only the index is per polygon vertex (or polygon, or edge). Materials
+        // are IndexToDirect too, but their only array is the index (into the model's materials).
+        let has_index = element.children.iter().any(|c| c.name.ends_with("Index"));
+        let only_index = reference != "Direct" && has_index;
+        for array in &mut element.children {
+            if only_index && !array.name.ends_with("Index") {
+                continue;
+            }
+            if let Some(value) = array.attributes.get_mut(0) {
+                remap_array(value, old_count, map, default)?;
+            }
+        }
+    }
+
+    let polygon_count = polygons.len();
+    let triangle_count = new_polygons.len();
+    if let Some(node) = geometry.child_mut("PolygonVertexIndex") {
+        node.attributes = vec![AttributeValue::ArrI32(new_indices)];
+    }
+    if let Some(node) = geometry.child_mut("Edges") {
+        node.attributes = vec![AttributeValue::ArrI32(new_edges)];
+    }
+
+    Ok(Some((polygon_count, triangle_count)))
+}
+
+/// Reorders the items of an array of `old_count` items, so item `i` is the old item `map[i]`.
+/// Items can be several values, ie. 3 for normals. Items without an old item get `default`.
+fn remap_array(
+    value: &mut AttributeValue,
+    old_count: usize,
+    map: &[Option<usize>],
+    default: f64,
+) -> anyhow::Result<()> {
+    fn remap<T: Copy>(
+        values: &[T],
+        old_count: usize,
+        map: &[Option<usize>],
+        default: T,
+    ) -> anyhow::Result<Vec<T>> {
+        if old_count == 0 || values.len() % old_count != 0 {
+            bail!(
+                "Layer element has {} values, which doesn't match {} items.",
+                values.len(),
+                old_count
+            );
+        }
+        let stride = values.len() / old_count;
+
+        let mut out = Vec::with_capacity(map.len() * stride);
+        for item in map {
+            match item {
+                Some(i) => out.extend_from_slice(&values[i * stride..(i + 1) * stride]),
+                None => out.extend(std::iter::repeat(default).take(stride)),
+            }
+        }
+        Ok(out)
+    }
+
+    match value {
+        AttributeValue::ArrBool(v) => *v = remap(v, old_count, map, default != 0.0)?,
+        AttributeValue::ArrI32(v) => *v = remap(v, old_count, map, default as i32)?,
+        AttributeValue::ArrI64(v) => *v = remap(v, old_count, map, default as i64)?,
+        AttributeValue::ArrF32(v) => *v = remap(v, old_count, map, default as f32)?,
+        AttributeValue::ArrF64(v) => *v = remap(v, old_count, map, default)?,
+        // Not an array, ie. a layer element's name.
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Splits a polygon into triangles. Returns the indices of each triangle's vertices in `points`,
+/// in the polygon's winding order.
+fn triangulate_polygon(points: &[Vector3<f64>]) -> Vec<[usize; 3]> {
+    let normal = polygon_normal(points);
+
+    // Twice the signed area of a triangle, positive if it winds the same way as the polygon.
+    let area = |a: usize, b: usize, c: usize| {
+        (points[b] - points[a])
+            .cross(points[c] - points[a])
+            .dot(normal)
+    };
+
+    if points.len() == 4 {
+        let ac = (points[2] - points[0]).magnitude2();
+        let bd = (points[3] - points[1]).magnitude2();
+        let split_ac = [[0, 1, 2], [0, 2, 3]];
+        let split_bd = [[0, 1, 3], [1, 2, 3]];
+        let folds = |split: &[[usize; 3]; 2]| split.iter().any(|t| area(t[0], t[1], t[2]) <= 0.0);
+
+        return if folds(&split_ac) && !folds(&split_bd) {
+            split_bd.to_vec()
+        } else if folds(&split_bd) && !folds(&split_ac) {
+            split_ac.to_vec()
+        } else if bd < ac {
+            split_bd.to_vec()
+        } else {
+            split_ac.to_vec()
+        };
+    }
+
+    // Ear clipping.
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = vec![];
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let corner = |i: usize| {
+            (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            )
+        };
+        let is_convex = |i: usize| {
+            let (a, b, c) = corner(i);
+            area(a, b, c) > 0.0
+        };
+        let is_ear = |i: usize| {
+            let (a, b, c) = corner(i);
+            is_convex(i)
+                && remaining
+                    .iter()
+                    .filter(|&&p| p != a && p != b && p != c)
+                    .all(|&p| !contains(points, normal, [a, b, c], p))
+        };
+
+        let ear = (0..n)
+            .find(|&i| is_ear(i))
+            .or_else(|| (0..n).find(|&i| is_convex(i)))
+            .unwrap_or(0);
+        let (a, b, c) = corner(ear);
+        triangles.push([a, b, c]);
+        remaining.remove(ear);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+/// True if point `p` is inside (or on the edge of) a triangle, which winds around `normal`.
+fn contains(points: &[Vector3<f64>], normal: Vector3<f64>, triangle: [usize; 3], p: usize) -> bool {
+    let p = points[p];
+    (0..3).all(|i| {
+        let a = points[triangle[i]];
+        let b = points[triangle[(i + 1) % 3]];
+        (b - a).cross(p - a).dot(normal) >= 0.0
+    })
+}
@@ -0,0 +1,156 @@
+use crate::tree::{connections, Node};
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+
+/// Model properties that are positions, and change with the unit.
+const POSITION_PROPERTIES: &[&str] = &[
+    "Lcl Translation",
+    "RotationOffset",
+    "RotationPivot",
+    "ScalingOffset",
+    "ScalingPivot",
+    "GeometricTranslation",
+];
+
+/// Matrices of a cluster (the skin of a single bone), which map between bone and mesh space.
+const CLUSTER_MATRICES: &[&str] = &["Transform", "TransformLink", "TransformAssociateModel"];
+
+/// Scales every position in the document by `factor`, so the scene keeps its size when the unit
+/// changes. For a unit change from `a` to `b` cm, the factor is `a / b`.
+///
+/// This scales:
+/// - Geometry vertices, including blend shapes.
+/// - The translation and pivots of models (see `POSITION_PROPERTIES`).
+/// - Animation curves on those properties, including their tangents.
+/// - The translation of cluster bind matrices and bind pose matrices.
+///
+/// Rotations and scales don't depend on the unit, so they are left alone. Matrices are scaled as
+/// `S * M * S^-1`, which only changes their translation.
+pub fn rescale(tree: &mut Node, factor: f64) {
+    let connections = connections(tree);
+
+    // Curve nodes animating a position, and the curves of each of their channels.
+    let curve_nodes: Vec<i64> = connections
+        .iter()
+        .filter(|c| c.kind == "OP")
+        .filter(|c| {
+            c.property
+                .as_deref()
+                .map_or(false, |p| POSITION_PROPERTIES.contains(&p))
+        })
+        .map(|c| c.child)
+        .collect();
+    let curves: Vec<i64> = connections
+        .iter()
+        .filter(|c| c.kind == "OP" && curve_nodes.contains(&c.parent))
+        .map(|c| c.child)
+        .collect();
+
+    let objects = match tree.child_mut("Objects") {
+        Some(objects) => objects,
+        None => return,
+    };
+
+    for object in &mut objects.children {
+        let id = object.object_id().unwrap_or(0);
+        match object.name.as_str() {
+            "Geometry" => {
+                if let Some(vertices) = object.child_mut("Vertices") {
+                    scale_values(vertices, factor);
+                }
+            }
+            "Model" => {
+                for name in POSITION_PROPERTIES {
+                    if let Some(p) = object.property_mut(name) {
+                        scale_property(p, factor);
+                    }
+                }
+            }
+            "Deformer" => {
+                for name in CLUSTER_MATRICES {
+                    if let Some(matrix) = object.child_mut(name) {
+                        scale_translation(matrix, factor);
+                    }
+                }
+            }
+            "Pose" => {
+                for pose_node in object.children_by_name_mut("PoseNode") {
+                    if let Some(matrix) = pose_node.child_mut("Matrix") {
+                        scale_translation(matrix, factor);
+                    }
+                }
+            }
+            "AnimationCurveNode" if curve_nodes.contains(&id) => {
+                for name in &["d|X", "d|Y", "d|Z"] {
+                    if let Some(p) = object.property_mut(name) {
+                        scale_property(p, factor);
+                    }
+                }
+            }
+            "AnimationCurve" if curves.contains(&id) => scale_curve(object, factor),
+            _ => {}
+        }
+    }
+}
+
+/// Scales every number of a node's attributes.
+fn scale_values(node: &mut Node, factor: f64) {
+    for attribute in &mut node.attributes {
+        match attribute {
+            AttributeValue::F32(v) => *v = (f64::from(*v) * factor) as f32,
+            AttributeValue::F64(v) => *v *= factor,
+            AttributeValue::ArrF32(values) => {
+                for v in values {
+                    *v = (f64::from(*v) * factor) as f32;
+                }
+            }
+            AttributeValue::ArrF64(values) => {
+                for v in values {
+                    *v *= factor;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Scales the values of a `P` node, skipping its name, type, label and flags.
+fn scale_property(p: &mut Node, factor: f64) {
+    for attribute in p.attributes.iter_mut().skip(4) {
+        if let AttributeValue::F64(v) = attribute {
+            *v *= factor;
+        }
+    }
+}
+
+/// Scales the translation of a 4x4 column-major matrix, stored as 16 doubles.
+fn scale_translation(matrix: &mut Node, factor: f64) {
+    if let Some(AttributeValue::ArrF64(m)) = matrix.attributes.get_mut(0) {
+        if m.len() == 16 {
+            for v in &mut m[12..15] {
+                *v *= factor;
+            }
+        }
+    }
+}
+
+/// Scales the keys of an animation curve.
+fn scale_curve(curve: &mut Node, factor: f64) {
+    if let Some(default) = curve.child_mut("Default") {
+        scale_values(default, factor);
+    }
+    if let Some(values) = curve.child_mut("KeyValueFloat") {
+        scale_values(values, factor);
+    }
+
+    // Each key's attributes are 4 floats: the right slope, the next key's left slope, and the
+    // packed weights and velocities. Slopes are in units per second, so they scale too.
+    if let Some(data) = curve.child_mut("KeyAttrDataFloat") {
+        if let Some(AttributeValue::ArrF32(data)) = data.attributes.get_mut(0) {
+            for key in data.chunks_mut(4) {
+                for slope in key.iter_mut().take(2) {
+                    *slope = (f64::from(*slope) * factor) as f32;
+                }
+            }
+        }
+    }
+}
@@ -1,37 +1,41 @@
-use clap::Arg;
-use fbxcel_dom::any::AnyDocument;
-use indexmap::IndexMap;
-use std::fs::File;
+use clap::{AppSettings, Arg, SubCommand};
 use std::io::Write;
-use std::io::{BufReader, BufWriter};
 use std::path::Path;
 
+mod ascii;
 mod checks;
+mod commands;
+mod config;
+mod diagnostic;
+mod dump;
+mod fix;
+mod report;
+mod scan;
+mod tree;
 mod utils;
+mod writer;
 
-use crate::utils::print_children;
-use checks::correct_coordinate_axis;
-use checks::is_fbx_binary;
-use checks::meshes_have_normals;
-use checks::no_quads;
-use checks::root_has_identity_transform;
-use checks::units_are_in_meters;
-use checks::bounding_box_size;
-use checks::no_scale_compensation;
-use itertools::Itertools;
-use crate::checks::no_namespaces;
+use crate::checks::Check;
+use crate::config::ConfigResolver;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::report::{FileMetadata, FileReport};
+use crate::utils::parse_document;
+use rayon::prelude::*;
 
 fn main() {
     // Custom logging formatting: "[ERROR] Error text."
+    // Warnings, info and fixes are only shown for this crate, so dependencies stay quiet.
     env_logger::Builder::new()
         .format(|buf, record| writeln!(buf, "[{}] {}", record.level(), record.args()))
         .filter_level(log::LevelFilter::Error)
+        .filter_module("fbx_sanitizer", log::LevelFilter::Info)
         .init();
 
     let cli_matches = clap::App::new("FBX Unity Sanitizer")
         .version("1.0")
         .author("John Austin")
-        .about("Checks fbx files (binary only) to make sure they will import cleanly into Unity.")
+        .about("Checks fbx files to make sure they will import cleanly into Unity.")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("summary").long("summary").takes_value(false).help(
             "Outputs a one-line summary for each fbx file passed in, rather than all errors.",
         ))
@@ -41,52 +45,338 @@ fn main() {
         .arg(Arg::with_name("dump-structure")
                  .long("dump-structure")
                  .takes_value(false)
-                 .help("Writes a loosely yaml-structured version of the binary file to <file>_structure.yml. Suitable for debugging and inspection."), )
-        .arg(Arg::with_name("files").multiple(true).takes_value(true).help("A set of fbx files to analyze.").required(true))
+                 .help("Writes the node tree of each file to <file>_structure.yml (or .json). Suitable for debugging and inspection."), )
+        .arg(Arg::with_name("dump-format")
+                 .long("dump-format")
+                 .takes_value(true)
+                 .possible_values(dump::FORMATS)
+                 .default_value("yaml")
+                 .help("The format of --dump-structure."))
+        .arg(Arg::with_name("path")
+                 .long("path")
+                 .takes_value(true)
+                 .value_name("PATH")
+                 .requires("dump-structure")
+                 .help("With --dump-structure, only dumps the nodes at this path, ie. Objects/Geometry."))
+        .arg(Arg::with_name("dump-output")
+                 .long("dump-output")
+                 .takes_value(true)
+                 .value_name("FILE")
+                 .requires("dump-structure")
+                 .help("With --dump-structure, writes the dump here instead of next to the fbx file. Use - for stdout."))
+        .arg(Arg::with_name("list-checks").long("list-checks").takes_value(false).help(
+            "Lists every available check, and whether it is enabled by default.",
+        ))
+        .arg(Arg::with_name("enable")
+                 .long("enable")
+                 .takes_value(true)
+                 .multiple(true)
+                 .use_delimiter(true)
+                 .value_name("CHECK")
+                 .help("Enables a check that is disabled by default. See --list-checks."))
+        .arg(Arg::with_name("disable")
+                 .long("disable")
+                 .takes_value(true)
+                 .multiple(true)
+                 .use_delimiter(true)
+                 .value_name("CHECK")
+                 .help("Disables a check. See --list-checks."))
+        .arg(Arg::with_name("config")
+                 .long("config")
+                 .takes_value(true)
+                 .value_name("FILE")
+                 .help("The project config file to use. By default, every fbx-sanitizer.toml in the working directory, the directory of each fbx file, and their parents is used."))
+        .arg(Arg::with_name("deny-warnings").long("deny-warnings").takes_value(false).help(
+            "Treats warnings as errors. Any warning will cause a non-zero exit code.",
+        ))
+        .arg(Arg::with_name("max-warnings")
+                 .long("max-warnings")
+                 .takes_value(true)
+                 .value_name("N")
+                 .help("Exits with a non-zero exit code if more than N warnings are found across all files."))
+        .arg(Arg::with_name("include")
+                 .long("include")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1)
+                 .value_name("GLOB")
+                 .help("When scanning directories, only checks files matching this glob. Can be repeated."))
+        .arg(Arg::with_name("exclude")
+                 .long("exclude")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1)
+                 .value_name("GLOB")
+                 .help("When scanning directories, skips files matching this glob. Can be repeated."))
+        .arg(Arg::with_name("jobs")
+                 .long("jobs")
+                 .short("j")
+                 .takes_value(true)
+                 .value_name("N")
+                 .help("The number of files to check in parallel. Defaults to the number of CPU cores."))
+        .arg(Arg::with_name("fix").long("fix").takes_value(false).help(
+            "Fixes the issues that can be fixed safely, and rewrites the file as binary fbx. Issues that remain are reported.",
+        ))
+        .arg(Arg::with_name("output")
+                 .long("output")
+                 .short("o")
+                 .takes_value(true)
+                 .value_name("FILE")
+                 .requires("fix")
+                 .help("With --fix, writes the fixed file here instead of overwriting the original. Only one file can be fixed this way."))
+        .arg(Arg::with_name("format")
+                 .long("format")
+                 .takes_value(true)
+                 .possible_values(report::FORMATS)
+                 .default_value("text")
+                 .help("The output format. Every format except text is printed to stdout."))
+        .arg(Arg::with_name("files").multiple(true).takes_value(true).help("A set of fbx files, or directories to search for fbx files.").required_unless("list-checks"))
+        .subcommand(SubCommand::with_name("convert")
+            .about("Converts an ASCII fbx file to binary fbx.")
+            .arg(Arg::with_name("input").required(true).value_name("INPUT").help("The ASCII fbx file to convert."))
+            .arg(Arg::with_name("output").required(true).value_name("OUTPUT").help("Where to write the binary fbx file.")))
+        .subcommand(SubCommand::with_name("diff")
+            .about("Compares two fbx files, ie. two exports of the same asset, and prints what changed between them.")
+            .arg(Arg::with_name("a").required(true).value_name("A").help("The original fbx file."))
+            .arg(Arg::with_name("b").required(true).value_name("B").help("The fbx file to compare it to."))
+            .arg(Arg::with_name("format")
+                     .long("format")
+                     .takes_value(true)
+                     .possible_values(commands::diff::FORMATS)
+                     .default_value("text")
+                     .help("The output format.")))
+        .subcommand(SubCommand::with_name("info")
+            .about("Prints a summary of each file's scene: its settings, and how many of each kind of object it has.")
+            .arg(Arg::with_name("files").required(true).multiple(true).help("The fbx files to summarize.")))
+        .subcommand(SubCommand::with_name("tree")
+            .about("Prints the model hierarchy of each file, with each model's type, local transform, geometry and materials.")
+            .arg(Arg::with_name("files").required(true).multiple(true).help("The fbx files to print.")))
         .get_matches_from(wild::args());
 
-    let files: Vec<&Path> = cli_matches
+    if let (name, Some(args)) = cli_matches.subcommand() {
+        let result = match name {
+            "convert" => commands::convert::run(args),
+            "diff" => commands::diff::run(args),
+            "info" => commands::info::run(args),
+            "tree" => commands::tree::run(args),
+            _ => unreachable!("Unknown subcommand {}", name),
+        };
+        if let Err(e) = result {
+            log::error!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if cli_matches.is_present("list-checks") {
+        for check in checks::all_checks() {
+            println!(
+                "{} [{}{}] {}\n    {}",
+                check.id(),
+                check.default_severity(),
+                if check.enabled_by_default() {
+                    ""
+                } else {
+                    ", disabled by default"
+                },
+                check.title(),
+                check.description()
+            );
+        }
+        return;
+    }
+
+    let enable: Vec<&str> = cli_matches
+        .values_of("enable")
+        .map(|v| v.collect())
+        .unwrap_or_default();
+    let disable: Vec<&str> = cli_matches
+        .values_of("disable")
+        .map(|v| v.collect())
+        .unwrap_or_default();
+    let explicit_config = cli_matches.value_of("config").map(Path::new);
+    let mut config_resolver = match ConfigResolver::new(explicit_config) {
+        Ok(resolver) => resolver,
+        Err(e) => {
+            log::error!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let max_warnings = match cli_matches
+        .value_of("max-warnings")
+        .map(str::parse::<usize>)
+    {
+        None => None,
+        Some(Ok(max)) => Some(max),
+        Some(Err(e)) => {
+            log::error!("Invalid value for --max-warnings: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(jobs) = cli_matches.value_of("jobs") {
+        let result = jobs
+            .parse::<usize>()
+            .map_err(anyhow::Error::from)
+            .and_then(|jobs| {
+                Ok(rayon::ThreadPoolBuilder::new()
+                    .num_threads(jobs)
+                    .build_global()?)
+            });
+        if let Err(e) = result {
+            log::error!("Invalid value for --jobs: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let paths: Vec<&Path> = cli_matches
         .values_of("files")
         .unwrap()
         .map(Path::new)
         .collect();
+    let include: Vec<&str> = cli_matches
+        .values_of("include")
+        .map(|v| v.collect())
+        .unwrap_or_default();
+    let exclude: Vec<&str> = cli_matches
+        .values_of("exclude")
+        .map(|v| v.collect())
+        .unwrap_or_default();
+    let files = match scan::collect_files(&paths, &include, &exclude) {
+        Ok(files) => files,
+        Err(e) => {
+            log::error!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
 
-    let mut any_errs = false;
+    if cli_matches.is_present("output") && files.len() != 1 {
+        log::error!(
+            "--output can only be used with a single fbx file. Found {} files.",
+            files.len()
+        );
+        std::process::exit(1);
+    }
+    let dump_output = cli_matches.value_of("dump-output");
+    if dump_output.map_or(false, |output| output != "-") && files.len() != 1 {
+        log::error!(
+            "--dump-output can only be used with a single fbx file, or -. Found {} files.",
+            files.len()
+        );
+        std::process::exit(1);
+    }
 
-    for path in files {
-        let extension = if let Some(ext) = path.extension() {
-            ext
-        } else {
-            log::error!(
-                "File path [{}] has no extension. It must be an .fbx file.",
-                path.display()
-            );
-            any_errs |= true;
-            continue;
-        };
+    // Resolve the settings for every file up front. This is cheap, and keeps the config
+    // resolver out of the worker threads.
+    let work: Vec<(&Path, anyhow::Result<Vec<Box<dyn Check>>>)> = files
+        .iter()
+        .map(|path| {
+            // Each file can have different settings, depending on the config files around it.
+            let checks = if scan::is_fbx(path) {
+                config_resolver
+                    .resolve(path)
+                    .and_then(|config| checks::select_checks(&config, &enable, &disable))
+            } else {
+                Ok(vec![])
+            };
+            (path.as_path(), checks)
+        })
+        .collect();
+
+    let mut reporter = match report::reporter(cli_matches.value_of("format").unwrap(), &cli_matches)
+    {
+        Ok(reporter) => reporter,
+        Err(e) => {
+            log::error!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
 
-        if extension.to_string_lossy().trim().to_lowercase() == "fbx" {
-            let result = check_fbx_file(path, &cli_matches);
+    let mut any_errs = false;
+    let mut total_warnings = 0;
+
+    // Files are checked in parallel, a batch at a time. Each batch is printed in input order once
+    // it is done, so output stays grouped per file and deterministic.
+    let batch_size = rayon::current_num_threads() * 4;
+    for batch in work.chunks(batch_size) {
+        let results: Vec<_> = batch
+            .par_iter()
+            .map(|(path, checks)| process_file(path, checks, &cli_matches))
+            .collect();
 
-            match result {
-                Err(e) => {
-                    log::error!("Could not parse fbx: {}", path.display());
-                    log::error!("{}", e);
+        for ((path, checks), result) in batch.iter().zip(results) {
+            let report = match result {
+                Err(failures) => {
                     any_errs |= true;
+                    FileReport {
+                        path,
+                        checks: &[],
+                        metadata: None,
+                        diagnostics: vec![],
+                        fixes: vec![],
+                        failures,
+                    }
+                }
+                Ok(CheckedFile {
+                    metadata,
+                    mut diagnostics,
+                    fixes,
+                }) => {
+                    if cli_matches.is_present("deny-warnings") {
+                        for diagnostic in &mut diagnostics {
+                            if diagnostic.severity() == Severity::Warning {
+                                diagnostic.severity = Some(Severity::Error);
+                            }
+                        }
+                    }
+
+                    // The checks were resolved, or the file could not have been checked.
+                    let checks = checks.as_ref().map(Vec::as_slice).unwrap_or(&[]);
+                    FileReport {
+                        path,
+                        checks,
+                        metadata: Some(metadata),
+                        diagnostics,
+                        fixes,
+                        failures: vec![],
+                    }
                 }
-                Ok(success) => {
-                    any_errs |= !success;
+            };
+
+            any_errs |= report.count(Severity::Error) > 0;
+            total_warnings += report.count(Severity::Warning);
+            if let Err(e) = reporter.file(&report) {
+                log::error!("Could not write report: {:#}", e);
+                std::process::exit(1);
+            }
+
+            // Dumps are written in order too, since they can go to stdout.
+            if cli_matches.is_present("dump-structure") && report.failures.is_empty() {
+                if let Err(e) = dump::write_structure(path, &cli_matches) {
+                    log::error!(
+                        "Could not dump the structure of {}: {:#}",
+                        path.display(),
+                        e
+                    );
+                    any_errs |= true;
                 }
             }
-        } else {
+        }
+    }
+
+    if let Err(e) = reporter.finish() {
+        log::error!("Could not write report: {:#}", e);
+        std::process::exit(1);
+    }
+
+    if let Some(max_warnings) = max_warnings {
+        if total_warnings > max_warnings {
             log::error!(
-                "file [{}] does not have an .fbx extension. Extension: [{}]",
-                path.display(),
-                extension.to_string_lossy()
+                "Found {} warnings, which is more than the maximum of {}.",
+                total_warnings,
+                max_warnings
             );
-            if !path.exists() {
-                log::error!("File [{}] does not exist.", path.display());
-            }
             any_errs |= true;
         }
     }
@@ -96,124 +386,114 @@ fn main() {
     }
 }
 
-/// Runs checks on the fbx file at the specified path.
-/// Returns true if there were no errors.
-pub fn check_fbx_file(path: &Path, args: &clap::ArgMatches) -> Result<bool, anyhow::Error> {
-    // println!("Parsing file: {}", path.display());
-    let file = File::open(path)?;
-
-    // You can also use raw `file`, but do buffering for better efficiency.
-    let reader = BufReader::new(file);
-    let mut errors = IndexMap::<&str, Vec<String>>::new();
-
-    // Check file
-    if !is_fbx_binary::verify(path)? {
-        errors
-            .entry("ASCII Format")
-            .or_insert(vec![])
-            .push("File is not saved in FBX binary format.".to_owned());
+/// The result of checking a single fbx file.
+struct CheckedFile {
+    metadata: FileMetadata,
+    diagnostics: Vec<Diagnostic>,
+    /// The changes made by `--fix`.
+    fixes: Vec<String>,
+}
+
+/// Checks a single file passed on the command line. This runs on a worker thread, so it doesn't
+/// print anything. On failure, returns the messages explaining why the file could not be checked.
+fn process_file(
+    path: &Path,
+    checks: &anyhow::Result<Vec<Box<dyn Check>>>,
+    args: &clap::ArgMatches,
+) -> Result<CheckedFile, Vec<String>> {
+    let extension = if let Some(ext) = path.extension() {
+        ext
     } else {
-        match AnyDocument::from_seekable_reader(reader)? {
-            AnyDocument::V7400(_, doc) => {
-                // Write out a loose yaml-like file for debugging.
-                if args.is_present("dump-structure") {
-                    let stem = path.file_stem().unwrap().to_str().unwrap();
-                    let mut yml_output = path.to_owned();
-                    yml_output.set_file_name(format!("{}_output.yml", stem));
-                    let mut writer = BufWriter::new(
-                        File::create(&yml_output).expect("Failed to open output file"),
-                    );
-                    print_children(&mut writer, &doc.tree().root(), 0)?;
-                    log::info!(
-                        "Dumped {} struct to {}",
-                        path.display(),
-                        yml_output.display()
-                    );
-                }
+        return Err(vec![format!(
+            "File path [{}] has no extension. It must be an .fbx file.",
+            path.display()
+        )]);
+    };
 
-                let mut is_highpoly: bool = false;
-                if let Some(name) = path.file_stem() {
-                    if let Ok(canonical_path) = path.canonicalize() {
-                        if name.to_string_lossy().ends_with("_HP")
-                            && canonical_path.to_string_lossy().contains("Raw~")
-                        {
-                            is_highpoly = true;
-                        }
-                    }
-                }
+    if !scan::is_fbx(path) {
+        let mut messages = vec![format!(
+            "file [{}] does not have an .fbx extension. Extension: [{}]",
+            path.display(),
+            extension.to_string_lossy()
+        )];
+        if !path.exists() {
+            messages.push(format!("File [{}] does not exist.", path.display()));
+        }
+        return Err(messages);
+    }
 
-                // Apply each error checker.
-                errors
-                    .entry("Units not in meters")
-                    .or_insert(vec![])
-                    .extend(units_are_in_meters::verify(&doc));
-                errors
-                    .entry("Incorrect axis")
-                    .or_insert(vec![])
-                    .extend(correct_coordinate_axis::verify(&doc)?);
-                errors
-                    .entry("Root does not have zero transform")
-                    .or_insert(vec![])
-                    .extend(root_has_identity_transform::verify(&doc)?);
-                errors
-                    .entry("No normals")
-                    .or_insert(vec![])
-                    .extend(meshes_have_normals::verify(&doc)?);
-                errors
-                    .entry("Scaling compensation is enabled")
-                    .or_insert(vec![])
-                    .extend(no_scale_compensation::verify(&doc)?);
-                errors
-                    .entry("Objects should not have namespaces")
-                    .or_insert(vec![])
-                    .extend(no_namespaces::verify(&doc)?);
-
-                // Disabled checks:
-                // This is only necessary for light baking, which we no longer use.
-                // errors
-                //     .entry("Mesh size is wrong")
-                //     .or_insert(vec![])
-                //     .extend(bounding_box_size::verify(&doc)?);
-
-                // Skip quad checks on the High Poly meshes in Raw~. These don't need to be triangulated.
-                if !is_highpoly {
-                    errors
-                        .entry("Contains quads")
-                        .or_insert(vec![])
-                        .extend(no_quads::verify(&doc)?);
-                }
+    let checks = checks.as_ref().map_err(|e| vec![format!("{:#}", e)])?;
 
-                // Disabled for now, until we can find a better way to report warnings.
-                // errors
-                //     .entry("Bad mesh naming")
-                //     .or_insert(vec![])
-                //     .extend(mesh_naming::verify(&doc)?);
-            }
-            _ => panic!("Got FBX document of unsupported version"),
-        }
+    let parse_failure = |path: &Path, e: anyhow::Error| {
+        vec![
+            format!("Could not parse fbx: {}", path.display()),
+            format!("{:#}", e),
+        ]
+    };
+
+    let (metadata, diagnostics) =
+        check_fbx_file(path, checks).map_err(|e| parse_failure(path, e))?;
+    if !args.is_present("fix") {
+        return Ok(CheckedFile {
+            metadata,
+            diagnostics,
+            fixes: vec![],
+        });
     }
 
-    // Print output
-    let total_errors: usize = errors.iter().map(|(_, errors)| errors.len()).sum();
-    let include_valid = args.is_present("include-valid");
-    if args.is_present("summary") {
-        let issues = errors
-            .iter()
-            .filter(|(_issue, errors)| !errors.is_empty())
-            .map(|(issue, _errors)| issue)
-            .join(",");
-        if total_errors > 0 || include_valid {
-            log::error!("{},{},{}", path.display(), total_errors, issues);
-        }
-    } else if total_errors > 0 {
-        log::error!("The file {} has {} errors:", path.display(), total_errors);
-        for (_issue, errors) in errors {
-            for error in errors {
-                log::error!("{}", error);
-            }
-        }
-        println!();
+    let output = args.value_of("output").map(Path::new).unwrap_or(path);
+    let fixes = fix::fix_file(path, output, checks, &diagnostics)
+        .map_err(|e| vec![format!("Could not fix {}: {:#}", path.display(), e)])?;
+
+    // Check the fixed file again, so the report and exit code reflect what was written.
+    let (metadata, diagnostics) =
+        check_fbx_file(output, checks).map_err(|e| parse_failure(output, e))?;
+    Ok(CheckedFile {
+        metadata,
+        diagnostics,
+        fixes,
+    })
+}
+
+/// Runs checks on the fbx file at the specified path.
+/// Returns the file's metadata, and every issue found, of all severities.
+pub fn check_fbx_file(
+    path: &Path,
+    checks: &[Box<dyn Check>],
+) -> Result<(FileMetadata, Vec<Diagnostic>), anyhow::Error> {
+    let mut diagnostics = Vec::<Diagnostic>::new();
+
+    for check in checks {
+        diagnostics.extend(stamp(check.as_ref(), check.run_on_file(path)?));
+    }
+
+    // Check file. ASCII files are converted to binary first, so they are parsed the same way.
+    let (bytes, binary) = ascii::read_as_binary(path)?;
+    let (version, doc) = parse_document(&bytes)?;
+    let metadata = FileMetadata::read(binary, version, &doc);
+
+    // Apply each error checker. A check that can't finish (ie. on corrupt data) is reported as an
+    // error of that check, so the other checks still run.
+    for check in checks {
+        let found = check.run(&doc).unwrap_or_else(|e| {
+            vec![
+                Diagnostic::new(format!("The check could not be completed: {:#}", e))
+                    .with_severity(Severity::Error),
+            ]
+        });
+        diagnostics.extend(stamp(check.as_ref(), found));
     }
 
-    Ok(total_errors == 0)
+    Ok((metadata, diagnostics))
+}
+
+/// Marks diagnostics as coming from the given check.
+fn stamp(check: &dyn Check, mut diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    for diagnostic in &mut diagnostics {
+        diagnostic.check_id = check.id();
+        diagnostic.severity = diagnostic
+            .severity
+            .or_else(|| Some(check.default_severity()));
+    }
+    diagnostics
 }
@@ -0,0 +1,118 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::report::{FileMetadata, FileReport, Reporter};
+use serde::Serialize;
+use std::io::Write;
+
+/// The JSON representation of a single file. This is the format of each line in `ndjson` output,
+/// and of each entry in `files` in `json` output.
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: String,
+    /// False if the file could not be checked. `failures` explains why.
+    checked: bool,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    failures: &'a [String],
+    metadata: Option<&'a FileMetadata>,
+    errors: usize,
+    warnings: usize,
+    diagnostics: Vec<JsonDiagnostic<'a>>,
+    /// The changes made by `--fix`. The diagnostics are of the fixed file.
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    fixes: &'a [String],
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    check: &'static str,
+    title: &'static str,
+    severity: Severity,
+    message: &'a str,
+    object: Option<JsonObject<'a>>,
+    measured: Option<&'a str>,
+    expected: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonObject<'a> {
+    id: i64,
+    name: &'a str,
+    class: &'a str,
+    subclass: &'a str,
+}
+
+impl<'a> JsonFile<'a> {
+    fn new(report: &'a FileReport) -> JsonFile<'a> {
+        JsonFile {
+            path: report.path.display().to_string(),
+            checked: report.failures.is_empty(),
+            failures: &report.failures,
+            metadata: report.metadata.as_ref(),
+            errors: report.count(Severity::Error),
+            warnings: report.count(Severity::Warning),
+            diagnostics: report
+                .diagnostics
+                .iter()
+                .map(|d| JsonDiagnostic::new(report, d))
+                .collect(),
+            fixes: &report.fixes,
+        }
+    }
+}
+
+impl<'a> JsonDiagnostic<'a> {
+    fn new(report: &FileReport, diagnostic: &'a Diagnostic) -> JsonDiagnostic<'a> {
+        JsonDiagnostic {
+            check: diagnostic.check_id,
+            title: report.title(diagnostic),
+            severity: diagnostic.severity(),
+            message: &diagnostic.message,
+            object: diagnostic.object.as_ref().map(|o| JsonObject {
+                id: o.id.raw(),
+                name: &o.name,
+                class: &o.class,
+                subclass: &o.subclass,
+            }),
+            measured: diagnostic.measured.as_deref(),
+            expected: diagnostic.expected.as_deref(),
+        }
+    }
+}
+
+/// Prints a single JSON document once every file is checked: `{ "files": [...] }`.
+#[derive(Default)]
+pub struct JsonReporter {
+    files: Vec<serde_json::Value>,
+}
+
+impl Reporter for JsonReporter {
+    fn file(&mut self, report: &FileReport) -> anyhow::Result<()> {
+        self.files
+            .push(serde_json::to_value(JsonFile::new(report))?);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        serde_json::to_writer_pretty(
+            &mut stdout,
+            &serde_json::json!({ "files": std::mem::take(&mut self.files) }),
+        )?;
+        writeln!(stdout)?;
+        Ok(())
+    }
+}
+
+/// Prints one line of JSON per file, as soon as the file is checked.
+pub struct NdjsonReporter;
+
+impl Reporter for NdjsonReporter {
+    fn file(&mut self, report: &FileReport) -> anyhow::Result<()> {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        serde_json::to_writer(&mut stdout, &JsonFile::new(report))?;
+        writeln!(stdout)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use crate::diagnostic::Severity;
+use crate::report::{FileReport, Reporter};
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// Prints a JUnit XML report once every file is checked, for CI test dashboards.
+///
+/// Each file is a testsuite, and each check that ran on it is a testcase. A testcase fails if its
+/// check found any errors. Warnings and info don't fail the testcase, and are written to its
+/// `system-out` instead. Files that could not be checked have a single testcase with an error.
+#[derive(Default)]
+pub struct JunitReporter {
+    suites: String,
+    tests: usize,
+    failures: usize,
+    errors: usize,
+}
+
+/// Escapes text for use in XML attributes and content.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML 1.0.
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Reporter for JunitReporter {
+    fn file(&mut self, report: &FileReport) -> anyhow::Result<()> {
+        let path = escape(&report.path.display().to_string());
+
+        if !report.failures.is_empty() {
+            writeln!(
+                self.suites,
+                r#"  <testsuite name="{0}" tests="1" failures="0" errors="1">
+    <testcase name="parse" classname="{0}">
+      <error message="The file could not be checked.">{1}</error>
+    </testcase>
+  </testsuite>"#,
+                path,
+                escape(&report.failures.join("\n")),
+            )?;
+            self.tests += 1;
+            self.errors += 1;
+            return Ok(());
+        }
+
+        let mut cases = String::new();
+        let mut failures = 0;
+        for check in report.checks {
+            let (errors, others): (Vec<_>, Vec<_>) = report
+                .diagnostics
+                .iter()
+                .filter(|d| d.check_id == check.id())
+                .partition(|d| d.severity() == Severity::Error);
+
+            write!(
+                cases,
+                r#"    <testcase name="{}" classname="{}">"#,
+                escape(check.id()),
+                path
+            )?;
+            if !errors.is_empty() {
+                failures += 1;
+                let messages = errors
+                    .iter()
+                    .map(|d| d.message.as_str())
+                    .collect::<Vec<_>>();
+                write!(
+                    cases,
+                    "\n      <failure message=\"{}\" type=\"error\">{}</failure>",
+                    escape(check.title()),
+                    escape(&messages.join("\n"))
+                )?;
+            }
+            if !others.is_empty() {
+                let messages = others
+                    .iter()
+                    .map(|d| format!("[{}] {}", d.severity(), d.message))
+                    .collect::<Vec<_>>();
+                write!(
+                    cases,
+                    "\n      <system-out>{}</system-out>",
+                    escape(&messages.join("\n"))
+                )?;
+            }
+            if errors.is_empty() && others.is_empty() {
+                writeln!(cases, "</testcase>")?;
+            } else {
+                writeln!(cases, "\n    </testcase>")?;
+            }
+        }
+
+        writeln!(
+            self.suites,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0">"#,
+            path,
+            report.checks.len(),
+            failures
+        )?;
+        self.suites.push_str(&cases);
+        writeln!(self.suites, "  </testsuite>")?;
+
+        self.tests += report.checks.len();
+        self.failures += failures;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        writeln!(stdout, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            stdout,
+            r#"<testsuites name="fbx-sanitizer" tests="{}" failures="{}" errors="{}">"#,
+            self.tests, self.failures, self.errors
+        )?;
+        write!(stdout, "{}", self.suites)?;
+        writeln!(stdout, "</testsuites>")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::checks::Check;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::utils::{get_creator, get_scene_info_string};
+use anyhow::bail;
+use fbxcel_dom::fbxcel::low::FbxVersion;
+use fbxcel_dom::v7400::Document;
+use serde::Serialize;
+use std::path::Path;
+
+mod json;
+mod junit;
+mod sarif;
+mod text;
+
+/// The output formats accepted by `--format`.
+pub const FORMATS: &[&str] = &["text", "json", "ndjson", "sarif", "junit"];
+
+/// Everything known about a single file after it was checked.
+pub struct FileReport<'a> {
+    pub path: &'a Path,
+    /// The checks that ran on the file. Empty if the file could not be checked.
+    pub checks: &'a [Box<dyn Check>],
+    /// None if the file could not be parsed.
+    pub metadata: Option<FileMetadata>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The changes made by `--fix`. The diagnostics are of the fixed file.
+    pub fixes: Vec<String>,
+    /// Why the file could not be checked. Empty if it was.
+    pub failures: Vec<String>,
+}
+
+impl FileReport<'_> {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity() == severity)
+            .count()
+    }
+
+    /// The title of the check that produced a diagnostic, falling back to its id.
+    pub fn title(&self, diagnostic: &Diagnostic) -> &'static str {
+        self.checks
+            .iter()
+            .find(|c| c.id() == diagnostic.check_id)
+            .map(|c| c.title())
+            .unwrap_or(diagnostic.check_id)
+    }
+}
+
+/// File-level information, read from the header of the document.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileMetadata {
+    pub binary: bool,
+    /// The raw FBX version, ie. 7400 for FBX 7.4.
+    pub fbx_version: Option<u32>,
+    /// The program that last saved the file, ie. "Blender" or "Maya".
+    pub application: Option<String>,
+    pub application_version: Option<String>,
+    /// The exporter, ie. "FBX SDK/FBX Plugins version 2020.0.1".
+    pub creator: Option<String>,
+}
+
+impl FileMetadata {
+    pub fn read(binary: bool, version: FbxVersion, doc: &Document) -> FileMetadata {
+        FileMetadata {
+            binary,
+            fbx_version: Some(version.raw()),
+            application: get_scene_info_string(doc, "LastSaved|ApplicationName").map(str::to_owned),
+            application_version: get_scene_info_string(doc, "LastSaved|ApplicationVersion")
+                .map(str::to_owned),
+            creator: get_creator(doc).map(str::to_owned),
+        }
+    }
+}
+
+/// Writes the results of a run. Files are passed in input order, as soon as they are checked.
+pub trait Reporter {
+    fn file(&mut self, report: &FileReport) -> anyhow::Result<()>;
+
+    /// Called once every file has been reported.
+    fn finish(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Creates the reporter for a `--format` value.
+pub fn reporter(format: &str, args: &clap::ArgMatches) -> anyhow::Result<Box<dyn Reporter>> {
+    Ok(match format {
+        "text" => Box::new(text::TextReporter {
+            summary: args.is_present("summary"),
+            include_valid: args.is_present("include-valid"),
+        }),
+        "json" => Box::new(json::JsonReporter::default()),
+        "ndjson" => Box::new(json::NdjsonReporter),
+        "sarif" => Box::new(sarif::SarifReporter::default()),
+        "junit" => Box::new(junit::JunitReporter::default()),
+        _ => bail!(
+            "Unknown format [{}]. Expected one of: {}.",
+            format,
+            FORMATS.join(", ")
+        ),
+    })
+}
@@ -0,0 +1,143 @@
+use crate::checks::{all_checks, Check};
+use crate::diagnostic::Severity;
+use crate::report::{FileReport, Reporter};
+use serde_json::{json, Value};
+use std::io::Write;
+
+/// Prints a SARIF 2.1 log once every file is checked, for code-scanning tools.
+///
+/// Every registered check is a rule, with its rationale as the help text. Each diagnostic is a
+/// result located at the fbx file, with the object it was found on as a logical location. Files
+/// that could not be checked are reported as tool execution notifications.
+pub struct SarifReporter {
+    /// The rules of the log, in order. Results refer to them by index.
+    rules: Vec<Box<dyn Check>>,
+    results: Vec<Value>,
+    notifications: Vec<Value>,
+}
+
+impl Default for SarifReporter {
+    fn default() -> SarifReporter {
+        SarifReporter {
+            rules: all_checks(),
+            results: vec![],
+            notifications: vec![],
+        }
+    }
+}
+
+/// SARIF's name for a severity.
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    }
+}
+
+/// The artifact location of a file. SARIF uris always use forward slashes.
+fn artifact_location(report: &FileReport) -> Value {
+    json!({ "uri": report.path.display().to_string().replace('\\', "/") })
+}
+
+impl Reporter for SarifReporter {
+    fn file(&mut self, report: &FileReport) -> anyhow::Result<()> {
+        for failure in &report.failures {
+            self.notifications.push(json!({
+                "level": "error",
+                "message": { "text": failure },
+                "locations": [{ "physicalLocation": { "artifactLocation": artifact_location(report) } }],
+            }));
+        }
+
+        for diagnostic in &report.diagnostics {
+            let mut location = json!({
+                "physicalLocation": { "artifactLocation": artifact_location(report) },
+            });
+            if let Some(object) = &diagnostic.object {
+                location["logicalLocations"] = json!([{
+                    "name": object.name,
+                    "fullyQualifiedName": format!("{}::{}", object.class, object.name),
+                    "kind": "object",
+                }]);
+            }
+
+            let mut result = json!({
+                "ruleId": diagnostic.check_id,
+                "level": level(diagnostic.severity()),
+                "message": { "text": diagnostic.message },
+                "locations": [location],
+            });
+            if let Some(index) = self
+                .rules
+                .iter()
+                .position(|c| c.id() == diagnostic.check_id)
+            {
+                result["ruleIndex"] = json!(index);
+            }
+
+            let mut properties = serde_json::Map::new();
+            if let Some(object) = &diagnostic.object {
+                properties.insert("objectId".to_owned(), json!(object.id.raw()));
+            }
+            if let Some(measured) = &diagnostic.measured {
+                properties.insert("measured".to_owned(), json!(measured));
+            }
+            if let Some(expected) = &diagnostic.expected {
+                properties.insert("expected".to_owned(), json!(expected));
+            }
+            if !properties.is_empty() {
+                result["properties"] = Value::Object(properties);
+            }
+
+            self.results.push(result);
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        let rules: Vec<Value> = self
+            .rules
+            .iter()
+            .map(|check| {
+                json!({
+                    "id": check.id(),
+                    "shortDescription": { "text": check.title() },
+                    "fullDescription": { "text": check.description() },
+                    "help": { "text": check.rationale() },
+                    "defaultConfiguration": {
+                        "enabled": check.enabled_by_default(),
+                        "level": level(check.default_severity()),
+                    },
+                })
+            })
+            .collect();
+
+        let execution_successful = self.notifications.is_empty();
+        let log = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "fbx-sanitizer",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "invocations": [{
+                    "executionSuccessful": execution_successful,
+                    "toolExecutionNotifications": std::mem::take(&mut self.notifications),
+                }],
+                "results": std::mem::take(&mut self.results),
+            }],
+        });
+
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        serde_json::to_writer_pretty(&mut stdout, &log)?;
+        writeln!(stdout)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::report::{FileReport, Reporter};
+use indexmap::IndexMap;
+use itertools::Itertools;
+
+/// The default, human readable output. Everything is printed through the logger.
+pub struct TextReporter {
+    /// Prints a one-line summary per file, rather than every issue.
+    pub summary: bool,
+    /// In summary mode, also prints a line for files without errors.
+    pub include_valid: bool,
+}
+
+impl Reporter for TextReporter {
+    fn file(&mut self, report: &FileReport) -> anyhow::Result<()> {
+        if !report.failures.is_empty() {
+            for failure in &report.failures {
+                log::error!("{}", failure);
+            }
+            return Ok(());
+        }
+
+        for fix in &report.fixes {
+            log::info!("Fixed {}: {}", report.path.display(), fix);
+        }
+
+        // Group the diagnostics by the check that found them, in the order the checks ran.
+        let mut errors = IndexMap::<&str, Vec<&Diagnostic>>::new();
+        for check in report.checks {
+            errors.insert(check.title(), vec![]);
+        }
+        for diagnostic in &report.diagnostics {
+            errors
+                .entry(report.title(diagnostic))
+                .or_insert(vec![])
+                .push(diagnostic);
+        }
+
+        let total_errors = report.count(Severity::Error);
+        let total_warnings = report.count(Severity::Warning);
+        let path = report.path;
+        if self.summary {
+            let issues = errors
+                .iter()
+                .filter(|(_issue, errors)| errors.iter().any(|d| d.severity() == Severity::Error))
+                .map(|(issue, _errors)| issue)
+                .join(",");
+            if total_errors > 0 || self.include_valid {
+                log::error!("{},{},{}", path.display(), total_errors, issues);
+            }
+        } else if !report.diagnostics.is_empty() {
+            if total_errors > 0 {
+                log::error!("The file {} has {} errors:", path.display(), total_errors);
+            }
+            if total_warnings > 0 {
+                log::warn!(
+                    "The file {} has {} warnings:",
+                    path.display(),
+                    total_warnings
+                );
+            }
+            for (_issue, errors) in errors {
+                for error in errors {
+                    match error.severity() {
+                        Severity::Error => log::error!("{}", error.message),
+                        Severity::Warning => log::warn!("{}", error.message),
+                        Severity::Info => log::info!("{}", error.message),
+                    }
+                }
+            }
+            println!();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use crate::utils::glob_set;
+use globset::GlobSet;
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+/// Directories that are never scanned, unless passed explicitly. These are Unity's generated
+/// folders, which contain copies of imported assets.
+const DEFAULT_EXCLUDED_DIRS: &[&str] = &["Library", "Temp"];
+
+/// Expands the paths passed on the command line into the list of files to check.
+///
+/// Files are returned as-is, even if they are not fbx files, so they can be reported. Directories
+/// are walked recursively for `.fbx` files, in a stable (sorted) order. Hidden folders and Unity's
+/// `Library` and `Temp` folders are skipped.
+///
+/// `include` and `exclude` are globs matched against paths relative to the scanned directory. If
+/// any includes are given, only files matching one of them are kept.
+pub fn collect_files(
+    paths: &[&Path],
+    include: &[&str],
+    exclude: &[&str],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let include = if include.is_empty() {
+        None
+    } else {
+        Some(glob_set(include)?)
+    };
+    let exclude = glob_set(exclude)?;
+
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            scan_dir(path, include.as_ref(), &exclude, &mut files)?;
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    Ok(files)
+}
+
+fn scan_dir(
+    dir: &Path,
+    include: Option<&GlobSet>,
+    exclude: &GlobSet,
+    files: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let walker = WalkDir::new(dir)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_excluded_dir(e));
+
+    for entry in walker {
+        let entry = entry?;
+        if !entry.file_type().is_file() || !is_fbx(entry.path()) {
+            continue;
+        }
+
+        let relative = entry.path().strip_prefix(dir)?;
+        if exclude.is_match(relative) {
+            continue;
+        }
+        if let Some(include) = include {
+            if !include.is_match(relative) {
+                continue;
+            }
+        }
+
+        files.push(entry.into_path());
+    }
+
+    Ok(())
+}
+
+fn is_excluded_dir(entry: &DirEntry) -> bool {
+    if !entry.file_type().is_dir() {
+        return false;
+    }
+
+    let name = entry.file_name().to_string_lossy();
+    name.starts_with('.') || DEFAULT_EXCLUDED_DIRS.contains(&name.as_ref())
+}
+
+/// True if the path has an `.fbx` extension, in any case.
+pub fn is_fbx(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().trim().to_lowercase() == "fbx")
+        .unwrap_or(false)
+}
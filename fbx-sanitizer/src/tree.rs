@@ -0,0 +1,171 @@
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use fbxcel_dom::fbxcel::tree::v7400::NodeHandle;
+
+/// An owned, editable copy of an fbx node tree.
+///
+/// fbxcel's tree is read-only, so fixes copy the document into this tree, edit it, and write it
+/// back out with `writer::write_binary`. The root node has an empty name and no attributes, and
+/// its children are the top-level nodes of the file (`FBXHeaderExtension`, `Objects`, ...).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub attributes: Vec<AttributeValue>,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new(name: impl Into<String>, attributes: Vec<AttributeValue>) -> Node {
+        Node {
+            name: name.into(),
+            attributes,
+            children: vec![],
+        }
+    }
+
+    /// Copies a node of a parsed document, and all of its children.
+    pub fn from_handle(handle: &NodeHandle<'_>) -> Node {
+        Node {
+            name: handle.name().to_owned(),
+            attributes: handle.attributes().to_vec(),
+            children: handle.children().map(|c| Node::from_handle(&c)).collect(),
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut Node> {
+        self.children.iter_mut().find(|c| c.name == name)
+    }
+
+    pub fn children_by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    pub fn children_by_name_mut<'a>(
+        &'a mut self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a mut Node> {
+        self.children.iter_mut().filter(move |c| c.name == name)
+    }
+
+    /// The object id of an object node (the first attribute of every node under `Objects`).
+    pub fn object_id(&self) -> Option<i64> {
+        match self.attributes.get(0)? {
+            AttributeValue::I64(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// The name of an object node, without its class. Object names are stored as
+    /// `"Name\x00\x01Class"`.
+    pub fn object_name(&self) -> Option<&str> {
+        let name = self.attributes.get(1)?.get_string()?;
+        Some(name.split("\u{0}\u{1}").next().unwrap_or(name))
+    }
+
+    /// The subclass of an object node (its third attribute), ie. "Mesh" for mesh models and
+    /// geometry, or "LimbNode" for bones.
+    pub fn object_subclass(&self) -> Option<&str> {
+        self.attributes.get(2)?.get_string()
+    }
+
+    /// Renames an object node, keeping its class.
+    pub fn set_object_name(&mut self, name: &str) {
+        if let Some(AttributeValue::String(full)) = self.attributes.get_mut(1) {
+            *full = match full.find("\u{0}\u{1}") {
+                Some(class) => format!("{}{}", name, &full[class..]),
+                None => name.to_owned(),
+            };
+        }
+    }
+
+    /// Finds a `P` node in this node's `Properties70` by property name.
+    pub fn property(&self, name: &str) -> Option<&Node> {
+        self.child("Properties70")?
+            .children_by_name("P")
+            .find(|p| p.attributes.get(0).and_then(AttributeValue::get_string) == Some(name))
+    }
+
+    /// The values of a property: the attributes after its name, type, label and flags.
+    pub fn property_values(&self, name: &str) -> Option<&[AttributeValue]> {
+        self.property(name)?.attributes.get(4..)
+    }
+
+    pub fn property_mut(&mut self, name: &str) -> Option<&mut Node> {
+        self.child_mut("Properties70")?
+            .children_by_name_mut("P")
+            .find(|p| p.attributes.get(0).and_then(AttributeValue::get_string) == Some(name))
+    }
+
+    /// Sets the value of a property in this node's `Properties70`, adding the property if it
+    /// doesn't exist. `type_name` and `label` are only used when the property is added, ie.
+    /// `"double"` and `"Number"`.
+    pub fn set_property(
+        &mut self,
+        name: &str,
+        type_name: &str,
+        label: &str,
+        values: Vec<AttributeValue>,
+    ) {
+        if let Some(p) = self.property_mut(name) {
+            p.attributes.truncate(4);
+            p.attributes.extend(values);
+            return;
+        }
+
+        if self.child("Properties70").is_none() {
+            self.children.push(Node::new("Properties70", vec![]));
+        }
+        let mut attributes = vec![
+            AttributeValue::String(name.to_owned()),
+            AttributeValue::String(type_name.to_owned()),
+            AttributeValue::String(label.to_owned()),
+            AttributeValue::String(String::new()),
+        ];
+        attributes.extend(values);
+        self.child_mut("Properties70")
+            .expect("Properties70 was just added")
+            .children
+            .push(Node::new("P", attributes));
+    }
+}
+
+/// A connection between two objects, or an object and a property, from the `Connections` node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    /// "OO" for object to object, "OP" for object to property.
+    pub kind: String,
+    pub child: i64,
+    pub parent: i64,
+    /// The parent's property, for "OP" connections. ie. "Lcl Translation" or "d|X".
+    pub property: Option<String>,
+}
+
+/// Reads every connection in the document. `root` is the root of the tree.
+pub fn connections(root: &Node) -> Vec<Connection> {
+    let node = match root.child("Connections") {
+        Some(node) => node,
+        None => return vec![],
+    };
+
+    node.children_by_name("C")
+        .filter_map(|c| {
+            let id = |i: usize| match c.attributes.get(i)? {
+                AttributeValue::I64(id) => Some(*id),
+                _ => None,
+            };
+            Some(Connection {
+                kind: c.attributes.get(0)?.get_string()?.to_owned(),
+                child: id(1)?,
+                parent: id(2)?,
+                property: c
+                    .attributes
+                    .get(3)
+                    .and_then(AttributeValue::get_string)
+                    .map(str::to_owned),
+            })
+        })
+        .collect()
+}
@@ -1,13 +1,40 @@
+use crate::tree::Node;
+use crate::writer::read_version;
+use anyhow::{bail, Context};
+use cgmath::Vector3;
+use fbxcel_dom::any::AnyDocument;
 use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use fbxcel_dom::fbxcel::low::FbxVersion;
 use fbxcel_dom::fbxcel::tree::v7400::NodeHandle;
 use fbxcel_dom::v7400::object::geometry::TypedGeometryHandle;
 use fbxcel_dom::v7400::object::model::TypedModelHandle;
 use fbxcel_dom::v7400::object::{ObjectId, TypedObjectHandle};
 use fbxcel_dom::v7400::Document;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::BufWriter;
-use std::io::Write;
+use std::io::Cursor;
+
+/// Compiles a set of path globs. Globs without a `/` match in any subdirectory, so `*_HP.fbx`
+/// behaves like `**/*_HP.fbx`. `*` never matches across directories.
+pub fn glob_set<S: AsRef<str>>(patterns: &[S]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        let glob = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_owned()
+        } else {
+            format!("**/{}", pattern)
+        };
+        builder.add(
+            GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid path glob [{}]", pattern))?,
+        );
+    }
+
+    Ok(builder.build()?)
+}
 
 /// Returns a useful name for a geometry. Either it's own given name, or the name of the first
 /// model that references this geometry.
@@ -48,43 +75,171 @@ pub fn get_model_roots(doc: &Document) -> Vec<TypedModelHandle<'_>> {
 
     results.values().cloned().collect()
 }
-#[allow(unused)]
-pub fn print_models(writer: &mut BufWriter<File>, doc: &Document, tabs: i32) {
-    for object in doc.objects() {
-        if let TypedObjectHandle::Model(model) = object.get_typed() {
-            print_children(writer, &object.node(), 0);
+
+/// The name of a UV set, ie. "map1", or its index if it has no name.
+pub fn uv_set_name(uv_node: NodeHandle<'_>) -> String {
+    let name = uv_node
+        .children_by_name("Name")
+        .next()
+        .and_then(|n| n.attributes().get(0))
+        .and_then(|a| a.get_string())
+        .filter(|name| !name.is_empty());
+    match name {
+        Some(name) => name.to_owned(),
+        None => match uv_node.attributes().get(0).and_then(|a| a.get_i32()) {
+            Some(index) => format!("UV{}", index),
+            None => "(no name)".to_owned(),
+        },
+    }
+}
+
+/// Returns the first string attribute of a child node, ie. the `MappingInformationType`.
+pub fn child_string<'a>(node: NodeHandle<'a>, name: &str) -> Option<&'a str> {
+    node.children_by_name(name)
+        .next()?
+        .attributes()
+        .iter()
+        .find_map(|a| a.get_string())
+}
+
+/// Checks that a `LayerElementUV` has a supported mapping and reference mode, and enough UVs
+/// (or UV indices) for the mesh. Returns why it is invalid, otherwise.
+pub fn validate_uv_set(
+    uv_node: NodeHandle<'_>,
+    polygon_vertex_count: usize,
+    control_point_count: usize,
+) -> Result<(), String> {
+    // UVs are either given for each corner of each polygon, or for each control point.
+    let mapping = child_string(uv_node, "MappingInformationType").unwrap_or("(none)");
+    let expected = match mapping {
+        "ByPolygonVertex" => polygon_vertex_count,
+        "ByVertice" | "ByVertex" | "ByControlPoint" => control_point_count,
+        _ => return Err(format!("mapping mode {} is not supported.", mapping)),
+    };
+
+    let uvs = uv_node
+        .children_by_name("UV")
+        .next()
+        .and_then(|n| n.attributes().get(0))
+        .and_then(|a| a.get_arr_f64())
+        .ok_or_else(|| "it has no UVs.".to_owned())?;
+    if uvs.len() % 2 != 0 {
+        return Err(format!(
+            "it has {} UV values, which is not a whole number of UVs.",
+            uvs.len()
+        ));
+    }
+    let uv_count = uvs.len() / 2;
+
+    let reference = child_string(uv_node, "ReferenceInformationType").unwrap_or("(none)");
+    match reference {
+        "Direct" => {
+            if uv_count != expected {
+                return Err(format!(
+                    "it has {} UVs, but {} are needed for mapping mode {}.",
+                    uv_count, expected, mapping
+                ));
+            }
+        }
+        // "Index" is the old name of "IndexToDirect".
+        "IndexToDirect" | "Index" => {
+            let indices = uv_node
+                .children_by_name("UVIndex")
+                .next()
+                .and_then(|n| n.attributes().get(0))
+                .and_then(|a| a.get_arr_i32())
+                .ok_or_else(|| "it has no UV indices.".to_owned())?;
+            if indices.len() != expected {
+                return Err(format!(
+                    "it has {} UV indices, but {} are needed for mapping mode {}.",
+                    indices.len(),
+                    expected,
+                    mapping
+                ));
+            }
+            let out_of_range = indices
+                .iter()
+                .filter(|&&i| i < 0 || i as usize >= uv_count)
+                .count();
+            if out_of_range > 0 {
+                return Err(format!(
+                    "{} UV indices are out of range. It has {} UVs.",
+                    out_of_range, uv_count
+                ));
+            }
         }
+        _ => return Err(format!("reference mode {} is not supported.", reference)),
     }
+
+    Ok(())
 }
 
-pub fn print_children(
-    writer: &mut BufWriter<File>,
-    node: &NodeHandle,
-    tabs: i32,
-) -> anyhow::Result<()> {
-    write!(
-        writer,
-        "{:indent$}Name: {} - ",
-        "",
-        node.name(),
-        indent = tabs as usize
-    )?;
-    for attr in node.attributes() {
-        write!(writer, "{:?}  ", attr)?;
+/// The normal of a polygon, facing the side its vertices wind counter-clockwise around. Uses
+/// Newell's method, which works for concave polygons. Its length is twice the polygon's area.
+pub fn polygon_normal(points: &[Vector3<f64>]) -> Vector3<f64> {
+    let mut normal = Vector3::new(0.0, 0.0, 0.0);
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
     }
+    normal
+}
 
-    writeln!(writer,)?;
+/// FBX times are in ticks of 1/46186158000 of a second.
+const TICKS_PER_SECOND: f64 = 46_186_158_000.0;
 
-    for child in node.children() {
-        print_children(writer, &child, tabs + 2)?;
+/// The length of an `AnimationStack`, in seconds. Uses its local time span, or its reference time
+/// span if it has no local one.
+pub fn stack_seconds(stack: &Node) -> Option<f64> {
+    let time = |name: &str| match stack.property_values(name)?.get(0)? {
+        AttributeValue::I64(t) => Some(*t),
+        _ => None,
+    };
+    let (start, stop) = time("LocalStart")
+        .zip(time("LocalStop"))
+        .or_else(|| time("ReferenceStart").zip(time("ReferenceStop")))?;
+    Some((stop - start) as f64 / TICKS_PER_SECOND)
+}
+
+/// True for the model subclasses of skeleton bones.
+pub fn is_bone(subclass: &str) -> bool {
+    matches!(subclass, "LimbNode" | "Limb" | "Root")
+}
+
+/// The kind of model, from its subclass, ie. "bone" or "mesh".
+pub fn model_type(subclass: &str) -> String {
+    match subclass {
+        "" => "unknown".to_owned(),
+        _ if is_bone(subclass) => "bone".to_owned(),
+        _ => subclass.to_lowercase(),
     }
+}
 
-    Ok(())
+/// Parses a binary fbx file (see `ascii::read_as_binary` for ASCII files).
+///
+/// Every FBX 7.x version is supported, including the 64-bit node offsets of FBX 7.5 and later.
+/// Older files, ie. FBX 6.1, are an error naming their version.
+pub fn parse_document(bytes: &[u8]) -> anyhow::Result<(FbxVersion, Box<Document>)> {
+    if let Some(version) = read_version(bytes) {
+        if !(7000..8000).contains(&version) {
+            bail!(
+                "FBX {}.{} files are not supported. Only FBX 7.x files can be read. Re-export the file as FBX 7.4 or later.",
+                version / 1000,
+                version % 1000 / 100
+            );
+        }
+    }
+
+    match AnyDocument::from_seekable_reader(Cursor::new(bytes))? {
+        AnyDocument::V7400(version, doc) => Ok((version, doc)),
+        _ => bail!("Unsupported FBX document version."),
+    }
 }
 
 /// Returns the Creator string from the document root, if it exists.
 /// Usually the FBX SDK (Maya, Max) or Blender
-#[allow(dead_code)]
 pub fn get_creator(doc: &Document) -> Option<&str> {
     let node = doc.tree().root().children_by_name("Creator").next()?;
 
@@ -101,16 +256,7 @@ pub fn get_creator(doc: &Document) -> Option<&str> {
 ///
 /// Returns None if there is no application name provided in the file.
 pub fn get_application_name(doc: &Document) -> Option<ApplicationName> {
-    let name = match doc.scene_info()?.get_property("LastSaved|ApplicationName") {
-        None => return None,
-        Some(p) => {
-            if let AttributeValue::String(s) = p.value_part().get(0)? {
-                s
-            } else {
-                return None;
-            }
-        }
-    };
+    let name = get_scene_info_string(doc, "LastSaved|ApplicationName")?;
 
     if name.contains("Blender") {
         Some(ApplicationName::Blender)
@@ -125,6 +271,15 @@ pub fn get_application_name(doc: &Document) -> Option<ApplicationName> {
     }
 }
 
+/// Returns a string property of FBXHeaderExtension->SceneInfo, ie. "LastSaved|ApplicationVersion".
+pub fn get_scene_info_string<'a>(doc: &'a Document, name: &str) -> Option<&'a str> {
+    if let AttributeValue::String(s) = doc.scene_info()?.get_property(name)?.value_part().get(0)? {
+        Some(s)
+    } else {
+        None
+    }
+}
+
 #[derive(Debug)]
 pub enum ApplicationName<'a> {
     Blender, // blender
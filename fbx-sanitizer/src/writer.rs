@@ -0,0 +1,257 @@
+use crate::tree::Node;
+use anyhow::bail;
+use fbxcel_dom::fbxcel::low::v7400::AttributeValue;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::convert::TryFrom;
+use std::io::Write;
+
+/// The magic at the start of every binary fbx file.
+const MAGIC: &[u8] = b"Kaydara FBX Binary  \x00\x1a\x00";
+
+/// The footer code written when the original file's is unknown. This is the code Blender writes,
+/// and matches the `FileId` and `CreationTime` Blender writes.
+const DEFAULT_FOOTER_CODE: [u8; 16] = [
+    0xfa, 0xbc, 0xab, 0x09, 0xd0, 0xc8, 0xd4, 0x66, 0xb1, 0x76, 0xfb, 0x83, 0x1c, 0xf7, 0x26, 0x7e,
+];
+
+/// The magic at the end of every binary fbx file.
+const FOOTER_MAGIC: [u8; 16] = [
+    0xf8, 0x5a, 0x8c, 0x6a, 0xde, 0xf5, 0xd9, 0x7e, 0xec, 0xe9, 0x0c, 0xe3, 0x75, 0x8f, 0x29, 0x0b,
+];
+
+/// Arrays smaller than this (in bytes) are written uncompressed, like the FBX SDK does.
+const COMPRESSION_THRESHOLD: usize = 128;
+
+/// Returns true if node headers of this version use 64-bit offsets (FBX 7.5 and later).
+fn is_64bit(version: u32) -> bool {
+    version >= 7500
+}
+
+/// Writes a binary fbx file.
+///
+/// `root` is the root of the tree (see `tree::Node`). `footer_code` is the 16 byte code that
+/// follows the nodes. The FBX SDK checks it against the `FileId` and `CreationTime` nodes, so it
+/// should be copied from the original file with `read_footer_code` when there is one.
+pub fn write_binary(
+    root: &Node,
+    version: u32,
+    footer_code: Option<[u8; 16]>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&version.to_le_bytes());
+
+    for node in &root.children {
+        write_node(&mut out, node, version)?;
+    }
+    write_null_record(&mut out, version);
+
+    // Footer.
+    out.extend_from_slice(&footer_code.unwrap_or(DEFAULT_FOOTER_CODE));
+    out.extend_from_slice(&[0; 4]);
+    // Pad to a multiple of 16. Files that are already aligned get a full 16 bytes of padding.
+    let padding = 16 - out.len() % 16;
+    out.extend(std::iter::repeat(0).take(padding));
+    out.extend_from_slice(&version.to_le_bytes());
+    out.extend_from_slice(&[0; 120]);
+    out.extend_from_slice(&FOOTER_MAGIC);
+
+    Ok(out)
+}
+
+/// Reads the FBX version from the header of a binary fbx file, ie. 7400 for FBX 7.4. Returns None
+/// if the file is not a binary fbx file.
+pub fn read_version(bytes: &[u8]) -> Option<u32> {
+    if !bytes.starts_with(MAGIC) {
+        return None;
+    }
+    read_u32(bytes, MAGIC.len())
+}
+
+/// Reads the footer code of a binary fbx file, by skipping over the top-level node records.
+/// Returns None if the file is not a valid binary fbx file.
+pub fn read_footer_code(bytes: &[u8]) -> Option<[u8; 16]> {
+    let version = read_version(bytes)?;
+
+    let mut offset = MAGIC.len() + 4;
+    loop {
+        let end_offset = if is_64bit(version) {
+            usize::try_from(read_u64(bytes, offset)?).ok()?
+        } else {
+            read_u32(bytes, offset)? as usize
+        };
+        if end_offset == 0 {
+            offset += null_record_len(version);
+            break;
+        }
+        if end_offset <= offset {
+            return None;
+        }
+        offset = end_offset;
+    }
+
+    let mut code = [0; 16];
+    code.copy_from_slice(bytes.get(offset..offset + 16)?);
+    Some(code)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let mut raw = [0; 4];
+    raw.copy_from_slice(bytes.get(offset..offset + 4)?);
+    Some(u32::from_le_bytes(raw))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let mut raw = [0; 8];
+    raw.copy_from_slice(bytes.get(offset..offset + 8)?);
+    Some(u64::from_le_bytes(raw))
+}
+
+fn null_record_len(version: u32) -> usize {
+    if is_64bit(version) {
+        25
+    } else {
+        13
+    }
+}
+
+fn write_null_record(out: &mut Vec<u8>, version: u32) {
+    out.extend(std::iter::repeat(0).take(null_record_len(version)));
+}
+
+/// Writes a header field, which is 64-bit in FBX 7.5 and later.
+fn write_offset(out: &mut Vec<u8>, at: usize, value: usize, version: u32) -> anyhow::Result<()> {
+    if is_64bit(version) {
+        out[at..at + 8].copy_from_slice(&(value as u64).to_le_bytes());
+    } else {
+        let value = match u32::try_from(value) {
+            Ok(value) => value,
+            Err(_) => bail!(
+                "The file is too large for FBX {}. Use FBX 7.5 or later.",
+                version
+            ),
+        };
+        out[at..at + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    Ok(())
+}
+
+fn write_node(out: &mut Vec<u8>, node: &Node, version: u32) -> anyhow::Result<()> {
+    if node.name.len() > u8::MAX as usize {
+        bail!("Node name [{}] is too long.", node.name);
+    }
+
+    // The header is filled in once the sizes are known.
+    let header = out.len();
+    let field_len = if is_64bit(version) { 8 } else { 4 };
+    out.extend(std::iter::repeat(0).take(field_len * 3));
+    out.push(node.name.len() as u8);
+    out.extend_from_slice(node.name.as_bytes());
+
+    let attributes_start = out.len();
+    for attribute in &node.attributes {
+        write_attribute(out, attribute)?;
+    }
+    let attributes_len = out.len() - attributes_start;
+
+    for child in &node.children {
+        write_node(out, child, version)?;
+    }
+    // Like the FBX SDK, nodes with children, or without any attributes, end with a null record.
+    if !node.children.is_empty() || node.attributes.is_empty() {
+        write_null_record(out, version);
+    }
+
+    let end = out.len();
+    write_offset(out, header, end, version)?;
+    write_offset(out, header + field_len, node.attributes.len(), version)?;
+    write_offset(out, header + field_len * 2, attributes_len, version)?;
+    Ok(())
+}
+
+fn write_attribute(out: &mut Vec<u8>, attribute: &AttributeValue) -> anyhow::Result<()> {
+    // Booleans are stored as 'Y' or 'T', whose lowest bit is the value.
+    let bool_byte = |v: bool| if v { b'Y' } else { b'T' };
+
+    match attribute {
+        AttributeValue::Bool(v) => {
+            out.push(b'C');
+            out.push(bool_byte(*v));
+        }
+        AttributeValue::I16(v) => {
+            out.push(b'Y');
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        AttributeValue::I32(v) => {
+            out.push(b'I');
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        AttributeValue::I64(v) => {
+            out.push(b'L');
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        AttributeValue::F32(v) => {
+            out.push(b'F');
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        AttributeValue::F64(v) => {
+            out.push(b'D');
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        AttributeValue::ArrBool(v) => {
+            let raw: Vec<u8> = v.iter().map(|v| bool_byte(*v)).collect();
+            write_array(out, b'b', v.len(), &raw)?;
+        }
+        AttributeValue::ArrI32(v) => {
+            let raw: Vec<u8> = v.iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+            write_array(out, b'i', v.len(), &raw)?;
+        }
+        AttributeValue::ArrI64(v) => {
+            let raw: Vec<u8> = v.iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+            write_array(out, b'l', v.len(), &raw)?;
+        }
+        AttributeValue::ArrF32(v) => {
+            let raw: Vec<u8> = v.iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+            write_array(out, b'f', v.len(), &raw)?;
+        }
+        AttributeValue::ArrF64(v) => {
+            let raw: Vec<u8> = v.iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+            write_array(out, b'd', v.len(), &raw)?;
+        }
+        AttributeValue::String(v) => {
+            out.push(b'S');
+            write_bytes(out, v.as_bytes())?;
+        }
+        AttributeValue::Binary(v) => {
+            out.push(b'R');
+            write_bytes(out, v)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) -> anyhow::Result<()> {
+    out.extend_from_slice(&u32::try_from(bytes.len())?.to_le_bytes());
+    out.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn write_array(out: &mut Vec<u8>, type_code: u8, len: usize, raw: &[u8]) -> anyhow::Result<()> {
+    out.push(type_code);
+    out.extend_from_slice(&u32::try_from(len)?.to_le_bytes());
+
+    if raw.len() < COMPRESSION_THRESHOLD {
+        out.extend_from_slice(&0u32.to_le_bytes());
+        write_bytes(out, raw)?;
+    } else {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(raw)?;
+        let compressed = encoder.finish()?;
+        out.extend_from_slice(&1u32.to_le_bytes());
+        write_bytes(out, &compressed)?;
+    }
+
+    Ok(())
+}
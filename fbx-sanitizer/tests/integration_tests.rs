@@ -1,5 +1,5 @@
 use assert_cmd::Command;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[test]
 fn blender_fbx_passes() {
@@ -107,4 +107,968 @@ fn file_with_namespaces_fails() {
     d.push("tests/maya_export_has_namespaces.fbx");
     command.args(&[d]);
     command.assert().failure();
-}
\ No newline at end of file
+}
+
+#[test]
+fn list_checks() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("--list-checks");
+    command
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("no_quads"))
+        .stdout(predicates::str::contains("mesh_naming"));
+}
+
+#[test]
+fn unknown_check_fails() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/blender_export_good.fbx");
+    command.arg("--disable").arg("not_a_check").arg(d);
+    command.assert().failure();
+}
+
+#[test]
+fn invalid_max_warnings_fails() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/blender_export_good.fbx");
+    command.arg("--max-warnings").arg("lots").arg(d);
+    command.assert().failure();
+}
+
+#[test]
+fn config_settings_are_applied() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/blender_export_good.fbx");
+    let mut config = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    config.push("tests/configs/tiny_bounds.toml");
+    command.arg("--config").arg(config).arg(d);
+    command.assert().failure();
+}
+
+#[test]
+fn config_severity_is_applied() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/blender_export_good.fbx");
+    let mut config = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    config.push("tests/configs/tiny_bounds_warning.toml");
+    command.arg("--config").arg(config).arg(d);
+    command.assert().success();
+}
+
+#[test]
+fn config_with_unknown_check_fails() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/blender_export_good.fbx");
+    let mut config = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    config.push("tests/configs/unknown_check.toml");
+    command.arg("--config").arg(config).arg(d);
+    command.assert().failure();
+}
+
+#[test]
+fn directory_config_is_discovered() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/Overrides/maya_export_good.fbx");
+    command.args(&[d]);
+    command.assert().failure();
+}
+
+#[test]
+fn working_directory_config_is_discovered() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_good.fbx");
+    // The config in Overrides applies to every file checked from there, wherever it is.
+    let mut working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    working_dir.push("tests/Overrides");
+    command.current_dir(working_dir).arg(d);
+    command.assert().failure();
+}
+
+#[test]
+fn missing_file_is_named() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/does_not_exist.fbx");
+    command.args(&[d]);
+    command
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Could not find ["))
+        .stderr(predicates::str::contains("does_not_exist.fbx]"));
+}
+
+#[test]
+fn config_overrides_match_paths() {
+    let mut config = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    config.push("tests/override_paths.toml");
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/blender_export_good.fbx");
+    command.arg("--config").arg(&config).arg(d);
+    command.assert().failure();
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/blender_export_good_caps.FBX");
+    command.arg("--config").arg(&config).arg(d);
+    command.assert().success();
+}
+
+#[test]
+fn directory_with_bad_files_fails() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests");
+    command.args(&[d]);
+    command.assert().failure();
+}
+
+#[test]
+fn directory_scan_honors_globs() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests");
+    command
+        .arg("--include")
+        .arg("*_good.fbx")
+        .arg("--exclude")
+        .arg("Overrides/**")
+        .arg(d);
+    command.assert().success();
+}
+
+#[test]
+fn parallel_output_matches_serial() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests");
+
+    let run = |jobs: &str| {
+        let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+        command
+            .arg("--summary")
+            .arg("--include-valid")
+            .arg("--jobs")
+            .arg(jobs)
+            .arg(&d);
+        let output = command.assert().failure().get_output().clone();
+        (output.stdout, output.stderr)
+    };
+
+    assert_eq!(run("1"), run("4"));
+}
+
+#[test]
+fn json_output_has_metadata() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_good.fbx");
+    command.arg("--format").arg("json").arg(d);
+    command
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"fbx_version\": 7400"));
+}
+
+#[test]
+fn ndjson_output_has_one_line_per_file() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut good = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    good.push("tests/maya_export_good.fbx");
+    let mut bad = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    bad.push("tests/maya_export_bad.fbx");
+    command.arg("--format").arg("ndjson").arg(&good).arg(&bad);
+
+    let output = command.assert().failure().get_output().clone();
+    let lines: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["errors"], 0);
+    assert!(lines[1]["errors"].as_u64().unwrap() > 0);
+    assert!(lines[1]["diagnostics"][0]["check"].is_string());
+}
+
+#[test]
+fn sarif_output_has_rules_and_results() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_bad.fbx");
+    command.arg("--format").arg("sarif").arg(&d);
+
+    let output = command.assert().failure().get_output().clone();
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+
+    let run = &log["runs"][0];
+    let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+    let units = rules
+        .iter()
+        .find(|r| r["id"] == "units_are_in_meters")
+        .unwrap();
+    assert_ne!(units["help"]["text"], units["fullDescription"]["text"]);
+    assert!(units["help"]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Unity will apply a scale when loading."));
+
+    let results = run["results"].as_array().unwrap();
+    assert!(!results.is_empty());
+    let uri = results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"]
+        .as_str()
+        .unwrap();
+    assert!(uri.ends_with("maya_export_bad.fbx"));
+}
+
+#[test]
+fn junit_output_has_a_testsuite_per_file() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut good = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    good.push("tests/maya_export_good.fbx");
+    let mut bad = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    bad.push("tests/maya_export_bad.fbx");
+    command.arg("--format").arg("junit").arg(&good).arg(&bad);
+
+    let output = command.assert().failure().get_output().clone();
+    let xml = String::from_utf8(output.stdout).unwrap();
+    assert!(xml.starts_with("<?xml"));
+    assert_eq!(xml.matches("<testsuite ").count(), 2);
+    assert!(xml.contains(r#"<testcase name="units_are_in_meters""#));
+    assert!(xml.contains("<failure "));
+}
+
+/// A path for a file written by a test. Each test run writes to its own directory, so concurrent
+/// runs don't overwrite each other's files.
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fbx_sanitizer_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn fix_removes_namespaces() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_has_namespaces.fbx");
+    let output = temp_path("fix_namespaces.fbx");
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command
+        .arg("--fix")
+        .arg("--output")
+        .arg(&output)
+        .arg("--format")
+        .arg("json")
+        .arg(&d);
+    let result = command.assert().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    // The fixed file is checked again, so it must have parsed.
+    let file = &report["files"][0];
+    assert_eq!(file["checked"], true);
+    assert!(file["fixes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|f| f.as_str().unwrap().starts_with("[no_namespaces]")));
+    assert!(file["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|d| d["check"] != "no_namespaces"));
+}
+
+#[test]
+fn fix_output_requires_single_file() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests");
+    let output = temp_path("fix_dir.fbx");
+    command.arg("--fix").arg("--output").arg(&output).arg(d);
+    command.assert().failure();
+}
+
+/// Reads the vertices of the first mesh in `path` back, through `--dump-structure`. Only the first
+/// 16 values are dumped, and the range of all of them.
+fn dump_vertices(path: &Path, config: &Path) -> serde_json::Value {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command
+        .arg("--config")
+        .arg(config)
+        .arg("--dump-structure")
+        .arg("--dump-format")
+        .arg("json")
+        .arg("--path")
+        .arg("Objects/Geometry/Vertices")
+        .arg("--dump-output")
+        .arg("-")
+        .arg(path);
+    let result = command.assert().success().get_output().clone();
+    let dump: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    dump["nodes"][0]["attributes"][0].clone()
+}
+
+#[test]
+fn fix_converts_units() {
+    let mut config = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    config.push("tests/configs/maya_in_meters.toml");
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_good.fbx");
+    let output = temp_path("fix_units.fbx");
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command
+        .arg("--config")
+        .arg(&config)
+        .arg("--fix")
+        .arg("--output")
+        .arg(&output)
+        .arg("--format")
+        .arg("json")
+        .arg(&d);
+    let result = command.assert().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    let file = &report["files"][0];
+    assert!(file["fixes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|f| f.as_str().unwrap().contains("scaling the scene by 0.01")));
+    assert!(file["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|d| d["check"] != "units_are_in_meters"));
+
+    // The cube is 1 unit (cm) wide, so it keeps its size at 0.01 units (m) wide.
+    let vertices = dump_vertices(&output, &config);
+    assert!((vertices["min"].as_f64().unwrap() + 0.005).abs() < 1e-12);
+    assert!((vertices["max"].as_f64().unwrap() - 0.005).abs() < 1e-12);
+}
+
+#[test]
+fn fix_converts_coordinate_axis() {
+    let mut config = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    config.push("tests/configs/maya_z_up.toml");
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_good.fbx");
+    let output = temp_path("fix_axis.fbx");
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command
+        .arg("--config")
+        .arg(&config)
+        .arg("--fix")
+        .arg("--output")
+        .arg(&output)
+        .arg("--format")
+        .arg("json")
+        .arg(&d);
+    let result = command.assert().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    let file = &report["files"][0];
+    assert!(file["fixes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|f| f.as_str().unwrap().starts_with("[correct_coordinate_axis]")));
+    assert!(file["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|d| d["check"] != "correct_coordinate_axis"));
+
+    // Converting Y-up to Z-up maps (x, y, z) to (x, -z, y), so the cube's first corner moves.
+    let vertices = dump_vertices(&output, &config);
+    let first: Vec<f64> = vertices["value"].as_array().unwrap()[..3]
+        .iter()
+        .map(|v| v.as_f64().unwrap())
+        .collect();
+    assert_eq!(first, vec![-0.5, -0.5, -0.5]);
+}
+
+#[test]
+fn fix_triangulates_meshes() {
+    // Copied out of Raw~, where high poly files are skipped.
+    let mut source = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    source.push("tests/Raw~/testquad_HP.fbx");
+    let d = temp_path("quads.fbx");
+    std::fs::copy(&source, &d).unwrap();
+
+    let fix = |output: &PathBuf| {
+        let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+        command
+            .arg("--fix")
+            .arg("--output")
+            .arg(output)
+            .arg("--format")
+            .arg("json")
+            .arg(&d);
+        let result = command.assert().get_output().clone();
+        serde_json::from_slice::<serde_json::Value>(&result.stdout).unwrap()
+    };
+
+    let first = temp_path("fix_quads_1.fbx");
+    let report = fix(&first);
+    let file = &report["files"][0];
+    assert!(file["fixes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|f| f.as_str().unwrap().starts_with("[no_quads]")));
+    assert!(file["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|d| d["check"] != "no_quads"));
+
+    // The triangulation is deterministic.
+    let second = temp_path("fix_quads_2.fbx");
+    fix(&second);
+    assert_eq!(
+        std::fs::read(&first).unwrap(),
+        std::fs::read(&second).unwrap()
+    );
+}
+
+/// Writes a copy of the ASCII cube (`maya_export_ascii.fbx`) to the temp directory, with each
+/// `(from, to)` replaced in its text. Fails if a replacement isn't found in the fixture.
+fn ascii_fixture(name: &str, replacements: &[(&str, &str)]) -> PathBuf {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_ascii.fbx");
+    let mut text = std::fs::read_to_string(&d).unwrap();
+    for (from, to) in replacements {
+        assert!(text.contains(from), "[{}] is not in the fixture", from);
+        text = text.replace(from, to);
+    }
+
+    let path = temp_path(&format!("{}.fbx", name));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn fix_warns_about_meshes_it_cannot_triangulate() {
+    // The first two triangles are merged into a quad, so the normals no longer match the polygons.
+    // The file is kept as ASCII, so nothing else is fixed.
+    let path = ascii_fixture(
+        "bad_quad",
+        &[(
+            "PolygonVertexIndex: *36 {\n\t\t\ta: 0,1,-4,0,3,-3,",
+            "PolygonVertexIndex: *34 {\n\t\t\ta: 0,1,3,-3,",
+        )],
+    );
+    let output = temp_path("fix_bad_quad.fbx");
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command
+        .arg("--disable")
+        .arg("is_fbx_binary")
+        .arg("--fix")
+        .arg("--output")
+        .arg(&output)
+        .arg("--format")
+        .arg("json")
+        .arg(&path);
+    let result = command.assert().failure().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    // Nothing was edited, so the file is copied as is.
+    assert_eq!(
+        std::fs::read(&output).unwrap(),
+        std::fs::read(&path).unwrap()
+    );
+
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(stderr.contains("Could not triangulate mesh [Crate]: Layer element has 108 values"));
+
+    let file = &report["files"][0];
+    assert!(file["fixes"].as_array().unwrap().is_empty());
+    assert!(file["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|d| d["check"] == "no_quads"));
+}
+
+#[test]
+fn ascii_file_is_checked() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_ascii.fbx");
+    command.arg("--format").arg("json").arg(&d);
+    let result = command.assert().failure().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    // The file is parsed, and only fails for being ASCII.
+    let file = &report["files"][0];
+    assert_eq!(file["checked"], true);
+    assert_eq!(file["metadata"]["binary"], false);
+    assert_eq!(file["metadata"]["fbx_version"], 7400);
+    assert_eq!(file["metadata"]["application"], "Maya");
+    let diagnostics = file["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["check"], "is_fbx_binary");
+    assert_eq!(
+        diagnostics[0]["message"],
+        "File is not saved in FBX binary format."
+    );
+}
+
+#[test]
+fn convert_ascii_to_binary() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_ascii.fbx");
+    let output = temp_path("convert_ascii.fbx");
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("convert").arg(&d).arg(&output);
+    command.assert().success();
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("--format").arg("json").arg(&output);
+    let result = command.assert().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    let file = &report["files"][0];
+    assert_eq!(file["checked"], true);
+    assert_eq!(file["metadata"]["binary"], true);
+    assert!(file["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|d| d["check"] != "is_fbx_binary"));
+}
+
+/// Writes a copy of the ASCII test file, saved as another FBX version.
+fn ascii_file_with_version(version: u32) -> PathBuf {
+    let header = format!("FBXVersion: {}", version);
+    ascii_fixture(
+        &format!("ascii_{}", version),
+        &[("FBXVersion: 7400", &header)],
+    )
+}
+
+#[test]
+fn fbx_7_5_files_are_checked() {
+    // Converting writes FBX 7.5, with 64-bit node offsets.
+    let d = ascii_file_with_version(7500);
+    let output = temp_path("convert_7500.fbx");
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("convert").arg(&d).arg(&output);
+    command.assert().success();
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("--format").arg("json").arg(&output);
+    let result = command.assert().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    let file = &report["files"][0];
+    assert_eq!(file["checked"], true);
+    assert_eq!(file["metadata"]["fbx_version"], 7500);
+}
+
+#[test]
+fn fbx_6_files_fail_cleanly() {
+    let ascii = ascii_file_with_version(6100);
+
+    // A binary header is enough, since the version is checked first.
+    let binary = temp_path("binary_6100.fbx");
+    let mut bytes = b"Kaydara FBX Binary  \x00\x1a\x00".to_vec();
+    bytes.extend_from_slice(&6100u32.to_le_bytes());
+    bytes.extend_from_slice(&[0; 64]);
+    std::fs::write(&binary, bytes).unwrap();
+
+    for d in &[ascii, binary] {
+        let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+        command.arg("--format").arg("json").arg(d);
+        let result = command.assert().failure().get_output().clone();
+        let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+        let file = &report["files"][0];
+        assert_eq!(file["checked"], false);
+        assert!(file["failures"].as_array().unwrap().iter().any(|f| f
+            .as_str()
+            .unwrap()
+            .contains("FBX 6.1 files are not supported")));
+    }
+}
+
+#[test]
+fn info_summarizes_scene() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_ascii.fbx");
+    command.arg("info").arg(&d);
+    command
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("7.4 (ASCII)"))
+        .stdout(predicates::str::contains("Maya 2020"))
+        .stdout(predicates::str::contains("Front:+Z,Up:+Y,Coord:+X"))
+        .stdout(predicates::str::contains("24 fps"))
+        .stdout(predicates::str::contains(
+            "1 (8 vertices, 12 polygons, 12 triangles)",
+        ));
+}
+
+#[test]
+fn tree_prints_model_hierarchy() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_ascii.fbx");
+    command.arg("tree").arg(&d);
+    command
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("└── Crate (mesh)"))
+        .stdout(predicates::str::contains(
+            "translation: (0, 0, 0)  rotation: (0, 0, 0)  scale: (1, 1, 1)",
+        ))
+        .stdout(predicates::str::contains("geometry: (no name) (mesh)"));
+}
+
+#[test]
+fn dump_structure_to_stdout() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_good.fbx");
+    command
+        .arg("--dump-structure")
+        .arg("--dump-format")
+        .arg("json")
+        .arg("--path")
+        .arg("Objects/Model")
+        .arg("--dump-output")
+        .arg("-")
+        .arg(&d);
+    let result = command.assert().success().get_output().clone();
+    let dump: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    assert_eq!(dump["fbx_version"], 7400);
+    let nodes = dump["nodes"].as_array().unwrap();
+    assert!(!nodes.is_empty());
+    for node in nodes {
+        assert_eq!(node["name"], "Model");
+        assert_eq!(node["attributes"][0]["type"], "i64");
+    }
+}
+
+#[test]
+fn dump_structure_truncates_arrays() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_ascii.fbx");
+    let output = temp_path("dump_structure.json");
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    // Disabled, so the ASCII file passes.
+    command
+        .arg("--disable")
+        .arg("is_fbx_binary")
+        .arg("--dump-structure")
+        .arg("--dump-format")
+        .arg("json")
+        .arg("--path")
+        .arg("Objects/Geometry/Vertices")
+        .arg("--dump-output")
+        .arg(&output)
+        .arg(&d);
+    command.assert().success();
+
+    let dump: serde_json::Value = serde_json::from_slice(&std::fs::read(&output).unwrap()).unwrap();
+    let vertices = &dump["nodes"][0]["attributes"][0];
+    assert_eq!(vertices["type"], "f64[]");
+    assert_eq!(vertices["length"], 24);
+    assert_eq!(vertices["truncated"], true);
+    assert_eq!(vertices["value"].as_array().unwrap().len(), 16);
+    assert_eq!(vertices["min"], -50.0);
+    assert_eq!(vertices["max"], 50.0);
+}
+
+#[test]
+fn diff_identical_files() {
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_good.fbx");
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("diff").arg(&d).arg(&d);
+    command
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No differences."));
+}
+
+#[test]
+fn diff_reports_changes() {
+    let mut a = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    a.push("tests/maya_export_ascii.fbx");
+    let b = ascii_fixture(
+        "diff",
+        &[
+            (
+                "P: \"UnitScaleFactor\", \"double\", \"Number\", \"\",1",
+                "P: \"UnitScaleFactor\", \"double\", \"Number\", \"\",100",
+            ),
+            (
+                "P: \"Lcl Translation\", \"Lcl Translation\", \"\", \"A\",0,0,0",
+                "P: \"Lcl Translation\", \"Lcl Translation\", \"\", \"A\",0,10,0",
+            ),
+        ],
+    );
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("diff").arg(&a).arg(&b);
+    command
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("  ~ UnitScaleFactor: 1 -> 100"))
+        .stdout(predicates::str::contains(
+            "      translation: (0, 0, 0) -> (0, 10, 0)",
+        ));
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command
+        .arg("diff")
+        .arg("--format")
+        .arg("json")
+        .arg(&a)
+        .arg(&b);
+    let result = command.assert().success().get_output().clone();
+    let diff: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    let changes = diff["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0]["section"], "settings");
+    assert_eq!(changes[0]["item"], "UnitScaleFactor");
+    assert_eq!(changes[0]["change"], "changed");
+    assert_eq!(changes[0]["fields"][0]["a"], "1");
+    assert_eq!(changes[0]["fields"][0]["b"], "100");
+    assert_eq!(changes[1]["section"], "models");
+    assert_eq!(changes[1]["item"], "Crate");
+    assert_eq!(changes[1]["fields"][0]["field"], "translation");
+}
+
+#[test]
+fn missing_uv_sets_are_warnings() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_good.fbx");
+    let mut config = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    config.push("tests/configs/two_uv_sets.toml");
+    command
+        .arg("--config")
+        .arg(config)
+        .arg("--format")
+        .arg("json")
+        .arg(d);
+    let result = command.assert().success().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    let diagnostic = report["files"][0]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["check"] == "meshes_have_uvs")
+        .unwrap()
+        .clone();
+    assert_eq!(diagnostic["severity"], "warning");
+    assert_eq!(diagnostic["measured"], "1");
+    assert_eq!(diagnostic["expected"], ">= 2");
+}
+
+#[test]
+fn invalid_uv_set_fails() {
+    // Only the first triangle has UV indices, and one of them is out of range.
+    let path = ascii_fixture(
+        "invalid_uvs",
+        &[(
+            "UVIndex: *36 {\n\t\t\t\ta: 0,1,3,0,3,2,0,1,3,0,3,2,0,1,3,0,3,2,0,1,3,0,3,2,0,1,3,0,3,2,0,1,3,0,3,2\n",
+            "UVIndex: *3 {\n\t\t\t\ta: 0,1,7\n",
+        )],
+    );
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg(&path);
+    command.assert().failure().stderr(predicates::str::contains(
+        "The mesh [Crate] has an invalid UV set [map1]: it has 3 UV indices, but 36 are needed for mapping mode ByPolygonVertex.",
+    ));
+}
+
+#[test]
+fn overlapping_lightmap_uvs_fail() {
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("tests/maya_export_ascii.fbx");
+    let mut config = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    config.push("tests/configs/lightmap_uv0.toml");
+    command
+        .arg("--config")
+        .arg(config)
+        .arg("--format")
+        .arg("json")
+        .arg(d);
+    let result = command.assert().failure().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    let diagnostics: Vec<&serde_json::Value> = report["files"][0]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|d| d["check"] == "lightmap_uvs")
+        .collect();
+    // The UVs are within 0-1, and every triangle has an area.
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["measured"], "100.0%");
+    assert_eq!(diagnostics[0]["object"]["name"], "Crate");
+}
+
+#[test]
+fn degenerate_triangles_fail() {
+    // The first triangle uses the same vertex twice.
+    let path = ascii_fixture("degenerate", &[("a: 0,1,-4,0,3,-3,", "a: 0,0,-4,0,3,-3,")]);
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg(&path);
+    command.assert().failure().stderr(predicates::str::contains(
+        "The mesh [Crate] has 1 degenerate polygons: 1 with repeated vertices, 0 with collapsed edges, and 0 with no area. \
+        The first are polygons 0.",
+    ));
+}
+
+#[test]
+fn invalid_values_are_reported() {
+    // The first vertex is NaN, and the second is infinite.
+    let path = ascii_fixture(
+        "nan",
+        &[(
+            "a: -50,-50,50,50,-50,50,",
+            "a: -1.#IND,-50,50,50,-50,1.#INF,",
+        )],
+    );
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command
+        .arg("--enable")
+        .arg("bounding_box_size")
+        .arg("--format")
+        .arg("json")
+        .arg(&path);
+    let result = command.assert().failure().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    // Every check still runs on the file.
+    let file = &report["files"][0];
+    assert_eq!(file["checked"], true);
+    let diagnostics = file["diagnostics"].as_array().unwrap();
+    assert!(!diagnostics.iter().any(|d| d["message"]
+        .as_str()
+        .unwrap()
+        .contains("could not be completed")));
+
+    let invalid: Vec<&serde_json::Value> = diagnostics
+        .iter()
+        .filter(|d| d["check"] == "no_invalid_values")
+        .collect();
+    assert_eq!(invalid.len(), 1);
+    assert_eq!(
+        invalid[0]["message"],
+        "The vertices of the mesh [Crate] contain 1 NaN values, 1 infinite values."
+    );
+    assert_eq!(invalid[0]["measured"], "NaN");
+}
+
+#[test]
+fn only_huge_transforms_are_reported() {
+    // A huge custom property is fine, but a huge translation is not.
+    let path = ascii_fixture(
+        "huge_transform",
+        &[
+            (
+                "P: \"ScalingMax\", \"Vector3D\", \"Vector\", \"\",0,0,0",
+                "P: \"ScalingMax\", \"Vector3D\", \"Vector\", \"\",0,0,100000000",
+            ),
+            (
+                "P: \"Lcl Translation\", \"Lcl Translation\", \"\", \"A\",0,0,0",
+                "P: \"Lcl Translation\", \"Lcl Translation\", \"\", \"A\",0,100000000,0",
+            ),
+        ],
+    );
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("--format").arg("json").arg(&path);
+    let result = command.assert().failure().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    let invalid: Vec<&serde_json::Value> = report["files"][0]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|d| d["check"] == "no_invalid_values")
+        .collect();
+    assert_eq!(invalid.len(), 1);
+    assert_eq!(
+        invalid[0]["message"],
+        "The Lcl Translation of the model [Crate] contain 1 values larger than 10000000."
+    );
+}
+
+#[test]
+fn flipped_and_unnormalized_normals_are_warnings() {
+    // The first triangle's normals point into the cube, and a normal of the second is too long.
+    let path = ascii_fixture(
+        "flipped",
+        &[(
+            "Normals: *108 {\n\t\t\t\ta: 0,0,1,0,0,1,0,0,1,0,0,1,",
+            "Normals: *108 {\n\t\t\t\ta: 0,0,-1,0,0,-1,0,0,-1,0,0,2,",
+        )],
+    );
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg("--format").arg("json").arg(&path);
+    let result = command.assert().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+
+    let normals: Vec<&serde_json::Value> = report["files"][0]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|d| d["check"] == "meshes_have_normals")
+        .collect();
+    assert_eq!(normals.len(), 2);
+    assert!(normals.iter().all(|d| d["severity"] == "warning"));
+    assert!(normals[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("1 normals that are not unit length"));
+    assert!(normals[1]["message"]
+        .as_str()
+        .unwrap()
+        .contains("1 faces whose normals point away from the face, ie. polygons 0."));
+}
+
+#[test]
+fn out_of_range_vertex_index_is_reported() {
+    // Normals are per vertex, and the first polygon uses a vertex the cube doesn't have.
+    let path = ascii_fixture(
+        "bad_vertex_index",
+        &[
+            (
+                "MappingInformationType: \"ByPolygonVertex\"\n\t\t\tReferenceInformationType: \"Direct\"\n\t\t\tNormals: *108 {",
+                "MappingInformationType: \"ByVertice\"\n\t\t\tReferenceInformationType: \"IndexToDirect\"\n\t\t\tNormalsIndex: *8 {\n\t\t\t\ta: 0,1,2,3,4,5,6,7\n\t\t\t}\n\t\t\tNormals: *108 {",
+            ),
+            ("a: 0,1,-4,0,3,-3,", "a: 9,1,-4,0,3,-3,"),
+        ],
+    );
+
+    let mut command = Command::cargo_bin("fbx_sanitizer").unwrap();
+    command.arg(&path);
+    command.assert().failure().stderr(predicates::str::contains(
+        "The mesh [Crate] has invalid normals: some polygon vertices have no normal.",
+    ));
+}